@@ -1,7 +1,20 @@
 use crate::common::*;
+use crate::cross_check::*;
+use crate::edf::*;
+use crate::load_file::*;
+use crate::msrp::*;
+use crate::nvic::*;
+use crate::priority::*;
 use crate::render_file::*;
+use crate::rtic::*;
+use crate::sensitivity::*;
+use crate::simulation::*;
 use crate::srp_analysis::*;
+use crate::stack::*;
+use crate::tasks::*;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::process;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -11,7 +24,29 @@ use structopt::StructOpt;
 execute examples:
 cargo run -- 
 cargo run -- -a
+cargo run -- --edf
+cargo run -- -d
+cargo run -- --policy same-or-higher
+cargo run -- --assign audsley
+cargo run -- --assign dm
+cargo run -- -s
+cargo run -- --simulate
+cargo run -- --simulate --horizon 1000 --offset T1=5 --offset T3=12
+cargo run -- --cross-check
+cargo run -- --priority-bits 2 --sub-priority-bits 1
+cargo run -- --assign dm --priority-bits 4 --sub-priority-bits 2
+cargo run -- --preemption-overhead 12 --lock-overhead 2 --unlock-overhead 2 --dispatch-overhead 6
+cargo run -- --tasks tasks/example.json
+cargo run -- --tasks tasks/example.toml -a
+cat tasks/example.json | cargo run -- --tasks -
+cargo run -- --tasks tasks/notation.toml --print-traces
+cargo run -- --tasks tasks/dual_core.toml
+cargo run -- --import-rtic tasks/rtic_app.rs > tasks/rtic_app.toml
 cargo run -- --help
+
+exits with code 2 if the system is not schedulable,
+and with code 1 on errors, including a failed cross-check
+and no feasible priority assignment
 "
 )]
 struct Opt {
@@ -20,22 +55,408 @@ struct Opt {
 
     #[structopt(short, long)]
     approx: bool,
+
+    /// Analyse under EDF, with SRP preemption levels derived from the relative
+    /// deadlines, by the processor demand test
+    #[structopt(
+        long,
+        conflicts_with_all = &["approx", "assign", "sensitivity", "simulate", "cross-check", "derivation", "policy"]
+    )]
+    edf: bool,
+
+    /// Preemption by tasks of the same priority, `strict` (`P(h)` > `P(t)`),
+    /// `same-or-higher` (`P(h)` >= `P(t)`) or `vector` (ordered by vector table index),
+    /// `strict` if omitted
+    #[structopt(long)]
+    policy: Option<Policy>,
+
+    /// Assign priorities before analysis, `audsley` (optimal priority assignment),
+    /// `dm` (deadline monotonic) or `rm` (rate monotonic)
+    #[structopt(long)]
+    assign: Option<Assignment>,
+
+    /// Report the maximum WCET and minimum inter-arrival time of each task,
+    /// and the critical scaling factor
+    #[structopt(short, long)]
+    sensitivity: bool,
+
+    /// Simulate the schedule under SRP and report the job event log, the
+    /// observed response times and a Gantt chart
+    #[structopt(long)]
+    simulate: bool,
+
+    /// Simulation horizon in cycles, by default the hyperperiod (plus the
+    /// largest offset), for --cross-check the synchronous busy period (plus
+    /// the offset of each scenario)
+    #[structopt(long)]
+    horizon: Option<u64>,
+
+    /// Release offset of the first job of a task in the simulation, `ID=N`
+    #[structopt(long, parse(try_from_str = parse_offset))]
+    offset: Vec<(String, u64)>,
+
+    /// Check that no simulated response time, under synchronous release and
+    /// worst-case blocking phasing, exceeds the analytic bound
+    #[structopt(long)]
+    cross_check: bool,
+
+    /// Number of NVIC priority bits of the target, the logical priorities are
+    /// mapped onto the hardware priorities before analysis. Tasks sharing a
+    /// preemption level are ordered by subpriority, under `--policy vector`
+    /// unless `same-or-higher` is given
+    #[structopt(long, conflicts_with = "edf")]
+    priority_bits: Option<u8>,
+
+    /// Number of subpriority bits of the NVIC priority grouping
+    #[structopt(long, requires = "priority-bits")]
+    sub_priority_bits: Option<u8>,
+
+    /// Context switch overhead of each preemption, in cycles
+    #[structopt(long, conflicts_with = "edf")]
+    preemption_overhead: Option<u32>,
+
+    /// Overhead of entering a critical section (raising the system ceiling), in cycles
+    #[structopt(long, conflicts_with = "edf")]
+    lock_overhead: Option<u32>,
+
+    /// Overhead of leaving a critical section (restoring the system ceiling), in cycles
+    #[structopt(long, conflicts_with = "edf")]
+    unlock_overhead: Option<u32>,
+
+    /// Interrupt entry and exit overhead of each task, in cycles
+    #[structopt(long, conflicts_with = "edf")]
+    dispatch_overhead: Option<u32>,
+
+    /// Report the iterations of the exact response time recurrence
+    #[structopt(short, long)]
+    derivation: bool,
+
+    /// Task set file (JSON or TOML), `-` reads from stdin.
+    /// The built-in example task set is used if omitted.
+    #[structopt(short, long, parse(from_os_str))]
+    tasks: Option<PathBuf>,
+
+    /// Task set format (`json` or `toml`), by default taken from the file extension
+    #[structopt(long)]
+    format: Option<Format>,
+
+    /// Print a skeleton TOML task set of the tasks, priorities and resources
+    /// of an RTIC application and exit, timing is left to be filled in
+    #[structopt(long, parse(from_os_str))]
+    import_rtic: Option<PathBuf>,
+
+    /// Print the task traces in bracket notation, `[id:start ... end]`
+    #[structopt(short, long)]
+    print_traces: bool,
+}
+
+fn parse_offset(s: &str) -> Result<(String, u64), String> {
+    match s.split_once('=') {
+        Some((id, n)) => match n.trim().parse() {
+            Ok(n) => Ok((id.trim().to_string(), n)),
+            Err(e) => Err(format!("invalid offset `{}`: {}", n, e)),
+        },
+        None => Err(format!("expected `ID=N`, found `{}`", s)),
+    }
 }
 
-pub fn cli(tasks: &Vec<Task>) {
+pub fn cli() {
     let opt = Opt::from_args();
-    let (ip, tr) = pre_analysis(&tasks);
-    let tot_util = tot_util(&tasks);
-    let analysis;
+    if let Some(path) = &opt.import_rtic {
+        let imported = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|src| scan_app(&src).map_err(|e| e.to_string()));
+        match imported {
+            Ok(rtic) => {
+                print!("{}", skeleton_toml(&rtic, &path.display().to_string()));
+                process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("error: {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        }
+    }
+    let tasks = match &opt.tasks {
+        Some(path) => match load_file(path, opt.format) {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        },
+        None => tasks(),
+    };
+
+    if opt.print_traces {
+        for t in &tasks {
+            println!("{}", t.trace);
+        }
+    }
+
+    if let Err(violations) = validate(&tasks) {
+        for v in &violations {
+            eprintln!("error: {}", v);
+        }
+        process::exit(1);
+    }
+
+    if opt.priority_bits.is_some() && opt.policy == Some(Policy::Strict) {
+        eprintln!(
+            "error: --policy strict ignores the tasks sharing an NVIC preemption level, \
+             use `vector` or `same-or-higher`"
+        );
+        process::exit(1);
+    }
+
+    // tasks partitioned to several cores are analysed by MSRP
+    let multicore = cores(&tasks).len() > 1;
+    if multicore
+        && (opt.edf || opt.assign.is_some() || opt.sensitivity || opt.simulate || opt.cross_check)
+    {
+        eprintln!(
+            "error: --edf, --assign, --sensitivity, --simulate and --cross-check \
+             support a single core only"
+        );
+        process::exit(1);
+    }
+
+    let config = Config {
+        approx: opt.approx,
+        record: opt.derivation,
+        policy: opt.policy.unwrap_or_default(),
+        overheads: Overheads {
+            preemption: opt.preemption_overhead.unwrap_or(0),
+            lock: opt.lock_overhead.unwrap_or(0),
+            unlock: opt.unlock_overhead.unwrap_or(0),
+            dispatch: opt.dispatch_overhead.unwrap_or(0),
+        },
+    };
 
-    if opt.approx {
-        analysis = srp_analysis(&tasks, &ip, &tr, true);
+    let input = tasks;
+    let mut assignment_error = None;
+    let prios = match opt.assign.map(|assignment| match assignment {
+        Assignment::Audsley => audsley(&input, &config),
+        Assignment::DeadlineMonotonic => deadline_monotonic(&input),
+        Assignment::RateMonotonic => rate_monotonic(&input),
+    }) {
+        Some(Ok(prios)) => Some(prios),
+        Some(Err(e)) => {
+            // the input priorities are analysed instead
+            eprintln!("error: {}", e);
+            assignment_error = Some(e);
+            None
+        }
+        None => None,
+    };
+    let assigned = match &prios {
+        Some(prios) => {
+            println!(
+                "{} priority assignment: {}",
+                opt.assign.unwrap(),
+                format_priorities(prios)
+            );
+            apply_priorities(&input, prios)
+        }
+        None => input.clone(),
+    };
+
+    // analyse the priorities as scheduled by the NVIC of the target
+    let mapping = opt.priority_bits.map(|bits| {
+        let target = match Target::new(bits, opt.sub_priority_bits.unwrap_or(0)) {
+            Ok(target) => target,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        };
+        let mapping = match nvic_mapping(&assigned, target) {
+            Ok(mapping) => mapping,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        };
+        for c in &mapping.collisions {
+            eprintln!("warning: {}", c);
+        }
+        mapping
+    });
+    let edf = if opt.edf {
+        match edf_analysis(&assigned) {
+            Ok(edf) => Some(edf),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    // under EDF, the preemption levels take the place of the priorities, for
+    // the resource ceilings and the shared stack
+    let tasks = match (&mapping, &edf) {
+        (Some(mapping), _) => mapping.apply(&assigned),
+        (_, Some(edf)) => apply_priorities(&assigned, &edf.levels()),
+        _ => assigned.clone(),
+    };
+    // tasks sharing an NVIC preemption level are served in the order of the
+    // mapping, without preempting each other
+    let config = match &mapping {
+        Some(_) => Config {
+            policy: opt.policy.unwrap_or(Policy::VectorOrder),
+            ..config
+        },
+        None => config,
+    };
+
+    let (ip, tr) = pre_analysis(&tasks);
+    let tot_util = tot_util_overheads(&tasks, &config.overheads);
+    let msrp = if multicore {
+        match msrp_analysis(&tasks, &config) {
+            Ok(msrp) => Some(msrp),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
     } else {
-        analysis = srp_analysis(&tasks, &ip, &tr, false);
+        None
+    };
+    // under EDF there are no fixed priority response times
+    let analysis = match (&edf, &msrp) {
+        (Some(_), _) => vec![],
+        (_, Some(msrp)) => msrp.task_analysis(&tasks),
+        _ => srp_analysis(&tasks, &ip, &tr, &config),
+    };
+
+    let verdict = match (&edf, &msrp) {
+        (Some(edf), _) => edf_schedulability(tot_util, edf),
+        (_, Some(msrp)) => msrp.verdict(),
+        _ => schedulability(tot_util, &analysis),
+    };
+
+    let mut data = make_data(&tot_util, &analysis, &verdict);
+    if !config.overheads.is_zero() {
+        data.insert("overheads".to_string(), overheads_data(&config.overheads));
+    }
+    if let Some(edf) = &edf {
+        data.insert("edf".to_string(), edf_data(edf));
+    }
+    if let Some(msrp) = &msrp {
+        data.insert("msrp".to_string(), msrp_data(msrp));
+    }
+    if let (Some(assignment), Some(_)) = (opt.assign, &prios) {
+        data.insert(
+            "priorities".to_string(),
+            priority_data(assignment, &input, &assigned),
+        );
+    }
+    if let Some(AssignmentError::Unassignable(e)) = &assignment_error {
+        data.insert("unassignable".to_string(), unassignable_data(e));
+    }
+    if let Some(mapping) = &mapping {
+        data.insert("nvic".to_string(), nvic_data(mapping, &assigned, &tasks));
+    }
+    data.insert("resources".to_string(), resource_data(&tasks, &ip, &tr));
+    if has_stack(&tasks) && multicore {
+        println!("Shared stack analysis skipped, each core has a stack of its own");
+    } else if has_stack(&tasks) {
+        let stack = stack_analysis(&tasks, &ip);
+        println!(
+            "Worst-case shared stack depth: {} ({})",
+            stack.depth,
+            stack.chain.join(" -> ")
+        );
+        data.insert("stack".to_string(), stack_data(&stack));
+    }
+    if opt.sensitivity {
+        data.insert(
+            "sensitivity".to_string(),
+            sensitivity_data(&sensitivity(&tasks, &config)),
+        );
     }
 
-    render_file(&tot_util, &analysis);
+    if opt.simulate {
+        for (id, _) in &opt.offset {
+            if !tasks.iter().any(|t| t.id == *id) {
+                eprintln!("error: offset given for unknown task `{}`", id);
+                process::exit(1);
+            }
+        }
+        let offsets: HashMap<String, u64> = opt.offset.iter().cloned().collect();
+        let horizon = match opt.horizon {
+            Some(horizon) => horizon,
+            None => hyperperiod_or_exit(&tasks) + offsets.values().max().unwrap_or(&0),
+        };
+        let sim_config = SimConfig {
+            horizon,
+            offsets,
+            max_events: Some(MAX_EVENTS),
+        };
+        let sim = match simulate(&tasks, &ip, &sim_config) {
+            Ok(sim) => sim,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        };
+        data.insert("simulation".to_string(), simulation_data(&tasks, &sim));
+    }
+
+    let mut checks_failed = false;
+    if opt.cross_check {
+        let horizon = opt.horizon.unwrap_or_else(|| match busy_period(&tasks) {
+            Some(length) => length,
+            None => {
+                eprintln!("error: the synchronous busy period does not end, give a --horizon");
+                process::exit(1);
+            }
+        });
+        let checks = match cross_check(&tasks, &config, horizon, MAX_EVENTS) {
+            Ok(checks) => checks,
+            Err(e) => {
+                eprintln!("error: cross-check failed, {}", e);
+                process::exit(1);
+            }
+        };
+        for c in checks.iter().filter(|c| c.violated()) {
+            eprintln!("error: cross-check failed, {}", c);
+            checks_failed = true;
+        }
+        data.insert("cross_check".to_string(), cross_check_data(&checks));
+    }
+
+    if let Err(e) = render_file(&data) {
+        eprintln!("error: failed to render report: {}", e);
+        process::exit(1);
+    }
     open_report();
+
+    if checks_failed || assignment_error.is_some() {
+        process::exit(1);
+    }
+
+    if verdict.schedulable {
+        println!("System is schedulable");
+    } else {
+        println!("System is NOT schedulable:");
+        for f in &verdict.failures {
+            println!("  {}", f);
+        }
+        // distinguishes an unschedulable system from errors (exit code 1)
+        process::exit(2);
+    }
+}
+
+fn hyperperiod_or_exit(tasks: &Tasks) -> u64 {
+    match hyperperiod(tasks) {
+        Some(h) => h,
+        None => {
+            eprintln!("error: the hyperperiod overflows, give a --horizon");
+            process::exit(1);
+        }
+    }
 }
 
 fn open_report() {
@@ -43,12 +464,10 @@ fn open_report() {
         Ok(exit_status) => {
             if exit_status.success() {
                 println!("Opening file in browser");
+            } else if let Some(code) = exit_status.code() {
+                println!("Command returned non-zero exit status {}!", code);
             } else {
-                if let Some(code) = exit_status.code() {
-                    println!("Command returned non-zero exit status {}!", code);
-                } else {
-                    println!("Command returned with unknown exit status!");
-                }
+                println!("Command returned with unknown exit status!");
             }
         }
         Err(why) => println!("Failure to execute command: {}", why),
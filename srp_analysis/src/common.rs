@@ -2,20 +2,34 @@ use std::collections::{HashMap, HashSet};
 
 // common data structures
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: String,
     pub prio: u8,
     pub deadline: u32,
     pub inter_arrival: u32,
+    // index in the interrupt vector table, orders tasks of the same priority
+    #[serde(default)]
+    pub vector: Option<u32>,
+    // release jitter, the maximal delay from arrival to release
+    #[serde(default)]
+    pub jitter: u32,
+    // stack usage in bytes, for the shared stack analysis
+    #[serde(default)]
+    pub stack: Option<u32>,
+    // the core the task is partitioned to, for multiprocessor analysis
+    #[serde(default)]
+    pub core: u32,
+    #[serde(deserialize_with = "crate::trace_notation::deserialize_trace")]
     pub trace: Trace,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trace {
     pub id: String,
     pub start: u32,
     pub end: u32,
+    #[serde(default)]
     pub inner: Vec<Trace>,
 }
 
@@ -43,6 +57,23 @@ pub fn pre_analysis(tasks: &Tasks) -> (IdPrio, TaskResources) {
     (ip, tr)
 }
 
+// The resources accessed by tasks on more than one core (global resources),
+// all other resources being local to a core
+pub fn global_resources(tasks: &Tasks) -> HashSet<String> {
+    let (_, tr) = pre_analysis(tasks);
+    let mut cores: HashMap<&String, HashSet<u32>> = HashMap::new();
+    for t in tasks {
+        for r in tr.get(&t.id).into_iter().flatten() {
+            cores.entry(r).or_default().insert(t.core);
+        }
+    }
+    cores
+        .into_iter()
+        .filter(|(_, cores)| cores.len() > 1)
+        .map(|(r, _)| r.clone())
+        .collect()
+}
+
 // helper functions
 fn update_prio(prio: u8, trace: &Trace, hm: &mut IdPrio) {
     if let Some(old_prio) = hm.get(&trace.id) {
@@ -69,3 +100,316 @@ fn update_tr(s: String, trace: &Trace, trmap: &mut TaskResources) {
         update_tr(s.clone(), trace, trmap);
     }
 }
+
+// A non-negative rational number, e.g., the CPU load `C(t)`/`A(t)`, with a
+// denominator of at most 64 bits. A sum is exact unless its denominator (the
+// LCM of the periods) exceeds 64 bits, it is then rounded up to a multiple
+// of 2^-63, so that a load is never under-estimated.
+#[derive(Debug, Clone, Copy)]
+pub struct Ratio {
+    pub num: u128,
+    pub den: u128,
+}
+
+impl Ratio {
+    pub fn new(num: u64, den: u64) -> Self {
+        Ratio::reduced(num as u128, den as u128)
+    }
+
+    fn reduced(num: u128, den: u128) -> Self {
+        let d = gcd(num, den).max(1);
+        Ratio {
+            num: num / d,
+            den: den / d,
+        }
+    }
+
+    // The numerator over 2^63, rounded up
+    fn scaled_up(self) -> u128 {
+        let (q, r) = (self.num / self.den, self.num % self.den);
+        // `r` < `den` <= 2^64, so `r * 2^63` fits
+        let frac = (r << 63).div_ceil(self.den);
+        q.saturating_mul(1 << 63).saturating_add(frac)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl From<u64> for Ratio {
+    fn from(n: u64) -> Self {
+        Ratio::new(n, 1)
+    }
+}
+
+impl std::ops::Add for Ratio {
+    type Output = Ratio;
+
+    fn add(self, other: Ratio) -> Ratio {
+        let d = gcd(self.den, other.den);
+        let den = self.den / d * other.den;
+        let num = match (
+            self.num.checked_mul(den / self.den),
+            other.num.checked_mul(den / other.den),
+        ) {
+            (Some(a), Some(b)) => a.checked_add(b),
+            _ => None,
+        };
+        match num {
+            Some(num) if den <= u64::MAX as u128 => Ratio::reduced(num, den),
+            _ => {
+                let num = self.scaled_up().saturating_add(other.scaled_up());
+                Ratio::reduced(num, 1 << 63)
+            }
+        }
+    }
+}
+
+impl PartialEq for Ratio {
+    fn eq(&self, other: &Ratio) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Ratio {}
+
+impl PartialOrd for Ratio {
+    fn partial_cmp(&self, other: &Ratio) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ratio {
+    fn cmp(&self, other: &Ratio) -> std::cmp::Ordering {
+        // integer parts first, the remainders are below the 64 bit denominators
+        (self.num / self.den)
+            .cmp(&(other.num / other.den))
+            .then_with(|| {
+                ((self.num % self.den) * other.den).cmp(&((other.num % other.den) * self.den))
+            })
+    }
+}
+
+// e.g., `13/15 (0.8667)`
+impl std::fmt::Display for Ratio {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{} ({:.4})", self.num, self.den, self.to_f64())
+        }
+    }
+}
+
+// validation of the task set
+
+/// A structural error in a task trace, located by the path to the offending
+/// section, e.g. `T2/R1@10/R2@12` (sections are identified by `id@start`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub task: String,
+    pub path: String,
+    pub msg: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "task {}: {}: {}", self.task, self.path, self.msg)
+    }
+}
+
+// Checks that task ids are unique, that inter-arrival times are non-zero and
+// that sections are well formed, nested within their parent without
+// overlapping siblings, and that no resource is re-claimed while held.
+// The analyses key their results by task id and assume a valid task set,
+// callers must validate it first.
+pub fn validate(tasks: &Tasks) -> Result<(), Vec<Violation>> {
+    let mut violations = vec![];
+    for (n, t) in tasks.iter().enumerate() {
+        if tasks[..n].iter().any(|other| other.id == t.id) {
+            violations.push(Violation {
+                task: t.id.clone(),
+                path: t.id.clone(),
+                msg: "task id is not unique".to_string(),
+            });
+        }
+        if t.inter_arrival == 0 {
+            violations.push(Violation {
+                task: t.id.clone(),
+                path: t.id.clone(),
+                msg: "inter-arrival time must be non-zero".to_string(),
+            });
+        }
+        let mut held = vec![];
+        validate_trace(t, &t.trace, t.trace.id.clone(), &mut held, &mut violations);
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+fn validate_trace(
+    task: &Task,
+    trace: &Trace,
+    path: String,
+    held: &mut Vec<String>,
+    violations: &mut Vec<Violation>,
+) {
+    let mut violation = |path: &str, msg: String| {
+        violations.push(Violation {
+            task: task.id.clone(),
+            path: path.to_string(),
+            msg,
+        })
+    };
+
+    if trace.end < trace.start {
+        violation(
+            &path,
+            format!("ends at {} before it starts at {}", trace.end, trace.start),
+        );
+    }
+    if held.contains(&trace.id) {
+        violation(
+            &path,
+            format!("re-claims resource {} while already held", trace.id),
+        );
+    }
+
+    let paths: Vec<String> = trace
+        .inner
+        .iter()
+        .map(|i| format!("{}/{}@{}", path, i.id, i.start))
+        .collect();
+
+    for (i, p) in trace.inner.iter().zip(&paths) {
+        if i.start < trace.start || i.end > trace.end {
+            violation(
+                p,
+                format!(
+                    "[{}, {}] is not within its parent [{}, {}]",
+                    i.start, i.end, trace.start, trace.end
+                ),
+            );
+        }
+    }
+    for (n, (a, pa)) in trace.inner.iter().zip(&paths).enumerate() {
+        for (b, pb) in trace.inner.iter().zip(&paths).skip(n + 1) {
+            if a.start < b.end && b.start < a.end {
+                violation(
+                    pb,
+                    format!(
+                        "[{}, {}] overlaps its sibling {} [{}, {}]",
+                        b.start, b.end, pa, a.start, a.end
+                    ),
+                );
+            }
+        }
+    }
+
+    held.push(trace.id.clone());
+    for (i, p) in trace.inner.iter().zip(paths) {
+        validate_trace(task, i, p, held, violations);
+    }
+    held.pop();
+}
+
+/// A task of `D(t)` = `A(t)`, with the trace in bracket notation, for tests.
+/// Other fields are set by struct update, e.g. `Task { core: 1, ..test_task(..) }`.
+#[cfg(test)]
+pub fn test_task(id: &str, prio: u8, inter_arrival: u32, trace: &str) -> Task {
+    Task {
+        id: id.to_string(),
+        prio,
+        deadline: inter_arrival,
+        inter_arrival,
+        vector: None,
+        jitter: 0,
+        stack: None,
+        core: 0,
+        trace: crate::trace_notation::parse_trace(trace).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod validate_tests {
+    use super::*;
+
+    fn task(trace: &str) -> Task {
+        test_task("T1", 1, 100, trace)
+    }
+
+    #[test]
+    fn test_valid() {
+        let tasks = vec![task("[T1:0 [R1:10 [R2:12 16] 20] [R1:20 28] 30]")];
+        assert_eq!(validate(&tasks), Ok(()));
+    }
+
+    #[test]
+    fn test_duplicate_id() {
+        let tasks = vec![task("[T1:0 10]"), task("[T1:0 20]")];
+        assert_eq!(
+            validate(&tasks).unwrap_err()[0].to_string(),
+            "task T1: T1: task id is not unique"
+        );
+    }
+
+    #[test]
+    fn test_violations() {
+        let tasks = vec![task("[T1:0 [R1:10 [R1:12 16] 20] [R2:18 32] [R3:8 4] 30]")];
+        let violations: Vec<String> = validate(&tasks)
+            .unwrap_err()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                "task T1: T1/R2@18: [18, 32] is not within its parent [0, 30]",
+                "task T1: T1/R2@18: [18, 32] overlaps its sibling T1/R1@10 [10, 20]",
+                "task T1: T1/R1@10/R1@12: re-claims resource R1 while already held",
+                "task T1: T1/R3@8: ends at 4 before it starts at 8",
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod ratio_tests {
+    use super::*;
+
+    #[test]
+    fn test_ratio() {
+        let r = Ratio::new(1, 3) + Ratio::new(1, 6);
+        assert_eq!((r.num, r.den), (1, 2));
+        assert!(Ratio::new(2, 3) < Ratio::new(3, 4));
+        assert_eq!(Ratio::new(5, 5), Ratio::from(1));
+
+        // the LCM of five coprime periods near 10^9 exceeds 64 bits
+        let periods = [
+            1_000_000_007,
+            1_000_000_009,
+            1_000_000_021,
+            1_000_000_033,
+            1_000_000_087,
+        ];
+        let load = periods
+            .iter()
+            .fold(Ratio::from(0), |l, p| l + Ratio::new(190_000_000, *p));
+        let exact: f64 = periods.iter().map(|p| 190_000_000.0 / *p as f64).sum();
+        assert!(load < Ratio::from(1));
+        assert!((load.to_f64() - exact).abs() < 1e-12);
+        assert!(load + Ratio::new(1, 10) > Ratio::from(1));
+    }
+}
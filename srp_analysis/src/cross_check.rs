@@ -0,0 +1,172 @@
+use crate::common::*;
+use crate::simulation::*;
+use crate::srp_analysis::*;
+use std::collections::HashMap;
+
+// Cross-check of the analytic response times against simulated schedules.
+//
+// The task set is simulated under synchronous release, and for each task `t`
+// under the worst-case blocking phasing: the lower priority task holding the
+// longest critical section of a resource with `π(r)` >= `P(t)` is released
+// first, and all other tasks are released the moment it locks the resource.
+// No observed response time may exceed the analytic bound of `response_time`.
+
+/// A release phasing to simulate
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub offsets: HashMap<String, u64>,
+}
+
+/// Cross-check result of a single task
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskCheck {
+    pub id: String,
+    /// The analytic response time, or the deadline miss
+    pub bound: Result<u64, DeadlineMiss>,
+    /// The largest simulated response time over all scenarios
+    pub observed: Option<u64>,
+    /// The scenario in which `observed` was seen
+    pub scenario: Option<String>,
+}
+
+impl TaskCheck {
+    /// True if the simulation exceeded the analytic bound
+    pub fn violated(&self) -> bool {
+        match (self.bound, self.observed) {
+            (Ok(bound), Some(observed)) => observed > bound,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for TaskCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "task {}: simulated response time {} ({}) exceeds the analytic bound {}",
+            self.id,
+            self.observed.unwrap_or(0),
+            self.scenario.as_deref().unwrap_or("-"),
+            self.bound.unwrap_or(0)
+        )
+    }
+}
+
+/// Returns the scenarios to simulate, synchronous release followed by the
+/// worst-case blocking phasing of each task that may be blocked
+pub fn scenarios(tasks: &Tasks, ip: &IdPrio, tr: &TaskResources, policy: Policy) -> Vec<Scenario> {
+    let mut scenarios = vec![Scenario {
+        name: "synchronous release".to_string(),
+        offsets: HashMap::new(),
+    }];
+
+    for task in tasks {
+        if let Some(b) = blocker(task, tasks, ip, tr, policy) {
+            let t = tasks.iter().find(|t| t.id == b.task).unwrap();
+            // the blocker starts first, without a resource it only has to start
+            let offset = match b.resource {
+                Some(_) => (b.start - t.trace.start) as u64,
+                None => 1,
+            };
+            let offsets = tasks
+                .iter()
+                .map(|t| (t.id.clone(), if t.id == b.task { 0 } else { offset }))
+                .collect();
+            scenarios.push(Scenario {
+                name: format!("{} blocked by {}", task.id, b),
+                offsets,
+            });
+        }
+    }
+    scenarios
+}
+
+/// Analyses and simulates the task set, each scenario from time 0 to its
+/// largest offset plus `horizon`, see `busy_period`. Each simulation is bounded
+/// by `max_events`.
+pub fn cross_check(
+    tasks: &Tasks,
+    config: &Config,
+    horizon: u64,
+    max_events: usize,
+) -> Result<Vec<TaskCheck>, TooManyEvents> {
+    let (ip, tr) = pre_analysis(tasks);
+    let analysis = srp_analysis(tasks, &ip, &tr, config);
+    let mut checks: Vec<TaskCheck> = analysis
+        .iter()
+        .map(|a| TaskCheck {
+            id: a.id.clone(),
+            bound: a.rt,
+            observed: None,
+            scenario: None,
+        })
+        .collect();
+
+    for scenario in scenarios(tasks, &ip, &tr, config.policy) {
+        let sim_config = SimConfig {
+            horizon: horizon + scenario.offsets.values().max().unwrap_or(&0),
+            offsets: scenario.offsets.clone(),
+            max_events: Some(max_events),
+        };
+        let sim = simulate(tasks, &ip, &sim_config)?;
+        for (check, s) in checks.iter_mut().zip(&sim.tasks) {
+            if s.max_rt > check.observed {
+                check.observed = s.max_rt;
+                check.scenario = Some(scenario.name.clone());
+            }
+        }
+    }
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod cross_check_tests {
+    use super::*;
+    use crate::tasks::tasks;
+
+    #[test]
+    fn test_example() {
+        let tasks = tasks();
+        let checks = cross_check(&tasks, &Config::default(), 200, MAX_EVENTS).unwrap();
+        assert!(checks.iter().all(|c| !c.violated()));
+        // T3 is blocked by T2 holding R2, R(T3) = 30 + 4
+        assert_eq!(checks[2].bound, Ok(34));
+        assert_eq!(checks[2].observed, Some(34));
+        assert_eq!(
+            checks[2].scenario.as_deref(),
+            Some("T3 blocked by T2 holding R2 at 12..16")
+        );
+    }
+
+    #[test]
+    fn test_ceiling_blocking() {
+        // M does not use R, but π(R) = P(H) > P(M), so L holding R blocks M
+        let tasks = vec![
+            test_task("L", 1, 100, "[L:0 [R:1 11] 12]"),
+            test_task("M", 2, 100, "[M:0 5]"),
+            test_task("H", 3, 100, "[H:0 [R:0 1] 2]"),
+        ];
+        let checks = cross_check(&tasks, &Config::default(), 100, MAX_EVENTS).unwrap();
+        assert!(checks.iter().all(|c| !c.violated()));
+        // released at 1, M waits until L unlocks R at 11 and for H
+        assert_eq!(checks[1].bound, Ok(5 + 10 + 2));
+        assert_eq!(checks[1].observed, Some(10 + 5 + 2));
+    }
+
+    #[test]
+    fn test_violation() {
+        // under the strict policy, T2 is assumed to never wait for T1
+        let tasks = vec![
+            test_task("T1", 1, 100, "[T1:0 10]"),
+            test_task("T2", 1, 100, "[T2:0 10]"),
+        ];
+        let checks = cross_check(&tasks, &Config::default(), 100, MAX_EVENTS).unwrap();
+        assert!(!checks[0].violated());
+        assert!(checks[1].violated());
+        assert_eq!(
+            checks[1].to_string(),
+            "task T2: simulated response time 20 (synchronous release) exceeds the analytic bound 10"
+        );
+    }
+}
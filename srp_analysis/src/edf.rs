@@ -0,0 +1,314 @@
+use crate::common::*;
+use crate::priority::*;
+use crate::simulation::hyperperiod;
+use crate::srp_analysis::*;
+
+// Earliest deadline first scheduling under SRP.
+//
+// Preemption levels `λ(t)` are derived from the relative deadlines, a shorter
+// `D(t)` giving a higher level, and tasks of equal deadline sharing a level.
+// Resource ceilings `π(r)` are computed over the preemption levels by
+// `pre_analysis`. Schedulability is checked by the processor demand test of
+// Baker, for each absolute deadline `L` within the synchronous busy period:
+//
+// `B(L)` + sum(`dbf(h, L)`) <= `L`,
+// `dbf(h, L)` = max(0, floor((`L` + `J(h)` - `D(h)`)/`A(h)`) + 1) * `C(h)`
+//
+// where `B(L)` is the longest critical section of a task with `D(t)` > `L`
+// of a resource with a ceiling at least the level of some task with
+// `D(h)` <= `L`.
+
+/// EDF analysis of a single task
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdfTask {
+    pub id: String,
+    pub deadline: u32,
+    pub level: u8,
+    pub wcet: u64,
+    /// The blocking time by tasks of lower preemption level
+    pub bt: u64,
+    pub blocker: Option<Blocker>,
+}
+
+/// An interval `L` in which the processor demand exceeds `L`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DemandMiss {
+    pub interval: u64,
+    /// The demand, including `blocking`
+    pub demand: u64,
+    pub blocking: u64,
+}
+
+/// Result of the EDF analysis
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdfAnalysis {
+    pub tasks: Vec<EdfTask>,
+    /// The synchronous busy period, including the longest critical section,
+    /// `None` if the CPU load exceeds 1
+    pub busy_period: Option<u64>,
+    /// The number of absolute deadlines checked
+    pub points: usize,
+    /// The first interval failing the demand test
+    pub miss: Option<DemandMiss>,
+}
+
+impl EdfAnalysis {
+    /// The preemption level of each task
+    pub fn levels(&self) -> Priorities {
+        self.tasks.iter().map(|t| (t.id.clone(), t.level)).collect()
+    }
+}
+
+/// Returns the preemption levels, 1, 2, ... by decreasing relative deadline,
+/// an error if there are more distinct deadlines than `u8` levels
+pub fn preemption_levels(tasks: &Tasks) -> Result<Priorities, String> {
+    let mut deadlines: Vec<u32> = tasks.iter().map(|t| t.deadline).collect();
+    deadlines.sort_by(|a, b| b.cmp(a));
+    deadlines.dedup();
+    if deadlines.len() > u8::MAX as usize {
+        return Err(format!(
+            "{} distinct deadlines exceed the {} preemption levels",
+            deadlines.len(),
+            u8::MAX
+        ));
+    }
+
+    Ok(tasks
+        .iter()
+        .map(|t| {
+            let level = deadlines.iter().position(|d| *d == t.deadline).unwrap() + 1;
+            (t.id.clone(), level as u8)
+        })
+        .collect())
+}
+
+/// Returns the task set with preemption levels in place of priorities
+pub fn leveled(tasks: &Tasks) -> Result<Tasks, String> {
+    Ok(apply_priorities(tasks, &preemption_levels(tasks)?))
+}
+
+/// Analyses the task set under EDF, the task set is assumed to pass `validate`
+pub fn edf_analysis(tasks: &Tasks) -> Result<EdfAnalysis, String> {
+    let leveled = leveled(tasks)?;
+    let (ip, tr) = pre_analysis(&leveled);
+
+    let edf_tasks: Vec<EdfTask> = leveled
+        .iter()
+        .map(|t| {
+            let blocker = blocker(t, &leveled, &ip, &tr, Policy::Strict);
+            EdfTask {
+                id: t.id.clone(),
+                deadline: t.deadline,
+                level: t.prio,
+                wcet: wcet(t),
+                bt: blocker.as_ref().map_or(0, |b| b.length()),
+                blocker,
+            }
+        })
+        .collect();
+
+    let mut analysis = EdfAnalysis {
+        tasks: edf_tasks,
+        busy_period: None,
+        points: 0,
+        miss: None,
+    };
+    if tot_util(tasks) > Ratio::from(1) {
+        return Ok(analysis);
+    }
+
+    // at full load the busy period need not end, the demand then repeats
+    // after the hyperperiod
+    let limit = hyperperiod(tasks).map_or(u64::MAX, |h| {
+        h.saturating_add(tasks.iter().map(|t| t.deadline as u64).max().unwrap_or(0))
+    });
+    let busy_period = busy_period(&leveled, &tr, limit);
+    analysis.busy_period = Some(busy_period);
+
+    // absolute deadlines within the busy period, in increasing order
+    let mut next: Vec<u64> = leveled
+        .iter()
+        .map(|t| t.deadline.saturating_sub(t.jitter) as u64)
+        .collect();
+    loop {
+        let interval = match next.iter().min() {
+            Some(l) if *l <= busy_period => *l,
+            _ => break,
+        };
+        for (t, n) in leveled.iter().zip(next.iter_mut()) {
+            while *n <= interval {
+                *n += t.inter_arrival.max(1) as u64;
+            }
+        }
+        analysis.points += 1;
+
+        let blocking = demand_blocking(&leveled, &ip, &tr, interval);
+        let demand = blocking + leveled.iter().map(|t| dbf(t, interval)).sum::<u64>();
+        if demand > interval {
+            analysis.miss = Some(DemandMiss {
+                interval,
+                demand,
+                blocking,
+            });
+            break;
+        }
+    }
+
+    Ok(analysis)
+}
+
+/// Returns the schedulability verdict of the system under EDF, requiring a
+/// CPU load of at most 1 and the processor demand test to hold
+pub fn edf_schedulability(tot_util: Ratio, analysis: &EdfAnalysis) -> Verdict {
+    let mut failures = vec![];
+
+    if tot_util > Ratio::from(1) {
+        failures.push(Failure::Overload { tot_util });
+    }
+    if let Some(miss) = analysis.miss {
+        failures.push(Failure::DemandExceeded {
+            interval: miss.interval,
+            demand: miss.demand,
+            blocking: miss.blocking,
+        });
+    }
+
+    Verdict {
+        schedulable: failures.is_empty(),
+        failures,
+    }
+}
+
+// The demand bound function, the execution demand of jobs of `task` with
+// both release and deadline within `0..=interval`
+fn dbf(task: &Task, interval: u64) -> u64 {
+    match (interval + task.jitter as u64).checked_sub(task.deadline as u64) {
+        Some(l) => (l / task.inter_arrival.max(1) as u64 + 1) * wcet(task),
+        None => 0,
+    }
+}
+
+// `B(L)`, see above
+fn demand_blocking(leveled: &Tasks, ip: &IdPrio, tr: &TaskResources, interval: u64) -> u64 {
+    // the lowest level of the tasks with a deadline within the interval
+    let level = match leveled
+        .iter()
+        .filter(|t| t.deadline as u64 <= interval)
+        .map(|t| t.prio)
+        .min()
+    {
+        Some(level) => level,
+        None => return 0,
+    };
+
+    leveled
+        .iter()
+        .filter(|t| t.deadline as u64 > interval)
+        .flat_map(|t| {
+            tr.get(&t.id)
+                .into_iter()
+                .flatten()
+                .filter(|r| ip[*r] >= level)
+                .map(move |r| wcet_resource(&t.trace, r))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+// The synchronous busy period, started by the longest critical section,
+// `w` = `B` + sum(ceil(`w`/`A(h)`) * `C(h)`), given a CPU load of at most 1,
+// bounded by `limit`
+fn busy_period(leveled: &Tasks, tr: &TaskResources, limit: u64) -> u64 {
+    let blocking = leveled
+        .iter()
+        .flat_map(|t| {
+            tr.get(&t.id)
+                .into_iter()
+                .flatten()
+                .map(move |r| wcet_resource(&t.trace, r))
+        })
+        .max()
+        .unwrap_or(0);
+
+    let mut w = blocking + leveled.iter().map(wcet).sum::<u64>();
+    loop {
+        let next = blocking
+            + leveled
+                .iter()
+                .map(|t| w.div_ceil(t.inter_arrival.max(1) as u64) * wcet(t))
+                .sum::<u64>();
+        if next == w || next >= limit {
+            return next.min(limit);
+        }
+        w = next;
+    }
+}
+
+#[cfg(test)]
+mod edf_tests {
+    use super::*;
+    use crate::tasks::tasks;
+
+    #[test]
+    fn test_edf() {
+        let tasks = tasks();
+        assert_eq!(
+            format_priorities(&preemption_levels(&tasks).unwrap()),
+            "T1=2, T2=1, T3=3"
+        );
+
+        let analysis = edf_analysis(&tasks).unwrap();
+        assert_eq!(analysis.busy_period, Some(150));
+        // 50, 100 (T1 and T3), 150
+        assert_eq!(analysis.points, 3);
+        assert_eq!(analysis.miss, None);
+        let bt: Vec<u64> = analysis.tasks.iter().map(|t| t.bt).collect();
+        assert_eq!(bt, vec![4, 0, 4]);
+        // R1 is used by T2 alone, its ceiling is the level of T2
+        let (ip, _) = pre_analysis(&apply_priorities(&tasks, &analysis.levels()));
+        assert_eq!((ip["R1"], ip["R2"]), (1, 3));
+        assert!(edf_schedulability(tot_util(&tasks), &analysis).schedulable);
+    }
+
+    #[test]
+    fn test_demand_miss() {
+        let task = |id: &str, deadline, inter_arrival, trace: &str| Task {
+            deadline,
+            ..test_task(id, 1, inter_arrival, trace)
+        };
+        // H meets its deadline only if L does not hold R when H is released
+        let tasks = vec![
+            task("H", 10, 20, "[H:0 [R:0 1] 5]"),
+            task("L", 100, 100, "[L:0 [R:10 16] 50]"),
+        ];
+        let analysis = edf_analysis(&tasks).unwrap();
+        assert_eq!(
+            analysis.miss,
+            Some(DemandMiss {
+                interval: 10,
+                demand: 11,
+                blocking: 6,
+            })
+        );
+        let verdict = edf_schedulability(tot_util(&tasks), &analysis);
+        assert_eq!(
+            verdict.failures[0].to_string(),
+            "processor demand 11 (including blocking 6) exceeds the interval 10"
+        );
+    }
+
+    #[test]
+    fn test_too_many_levels() {
+        let tasks: Tasks = (1..=256)
+            .map(|d| Task {
+                deadline: d,
+                ..test_task(&format!("T{}", d), 1, 1000, "[T:0 1]")
+            })
+            .collect();
+        assert_eq!(
+            edf_analysis(&tasks).unwrap_err(),
+            "256 distinct deadlines exceed the 255 preemption levels"
+        );
+        assert!(preemption_levels(&tasks[1..].to_vec()).is_ok());
+    }
+}
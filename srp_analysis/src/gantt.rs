@@ -0,0 +1,194 @@
+use crate::common::*;
+use crate::simulation::*;
+use std::fmt::Write;
+
+// SVG Gantt chart of a simulated schedule, one lane per task. Execution is
+// drawn as a bar, each held resource shades a nested band inside the bar
+// (so the nesting of critical sections shows as increasingly dark bands),
+// releases are marked by triangles below the lane and deadlines by red ticks.
+
+const LABEL: f64 = 60.0;
+const WIDTH: f64 = 1000.0;
+const LANE: f64 = 36.0;
+const BAR: f64 = 24.0;
+const INSET: f64 = 4.0;
+const AXIS: f64 = 24.0;
+
+/// Returns the schedule of `simulation` as an SVG image
+pub fn gantt(tasks: &Tasks, simulation: &Simulation) -> String {
+    let horizon = simulation.horizon.max(1);
+    let x = |t: u64| LABEL + t as f64 * WIDTH / horizon as f64;
+    let lane = |id: &str| tasks.iter().position(|t| t.id == id).unwrap_or(0) as f64 * LANE;
+    let height = tasks.len() as f64 * LANE + AXIS;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+        LABEL + WIDTH + 10.0,
+        height
+    );
+
+    for (i, t) in tasks.iter().enumerate() {
+        let y = i as f64 * LANE;
+        let _ = writeln!(
+            svg,
+            r##"<text x="4" y="{}">{}</text><line x1="{}" y1="{}" x2="{}" y2="{}" stroke="#ccc"/>"##,
+            y + BAR * 0.75,
+            escape(&t.id),
+            LABEL,
+            y + LANE - 4.0,
+            x(horizon),
+            y + LANE - 4.0
+        );
+    }
+
+    for s in &simulation.slices {
+        let y = lane(&s.task);
+        let w = x(s.end) - x(s.start);
+        let title = if s.held.is_empty() {
+            format!("{}#{} {}..{}", s.task, s.job, s.start, s.end)
+        } else {
+            format!(
+                "{}#{} {}..{} holding {}",
+                s.task,
+                s.job,
+                s.start,
+                s.end,
+                s.held.join(", ")
+            )
+        };
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{:.2}" y="{}" width="{:.2}" height="{}" fill="#4c8bf5"><title>{}</title></rect>"##,
+            x(s.start),
+            y,
+            w,
+            BAR,
+            escape(&title)
+        );
+        for (depth, r) in s.held.iter().enumerate() {
+            let inset = (depth + 1) as f64 * INSET;
+            if inset * 2.0 >= BAR {
+                break;
+            }
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{:.2}" y="{}" width="{:.2}" height="{}" fill="black" fill-opacity="0.25"><title>{}</title></rect>"#,
+                x(s.start),
+                y + inset,
+                w,
+                BAR - 2.0 * inset,
+                escape(r)
+            );
+        }
+    }
+
+    for e in &simulation.events {
+        let y = lane(&e.task);
+        match e.kind {
+            EventKind::Release => {
+                let _ = writeln!(
+                    svg,
+                    r#"<path d="M{:.2} {} l-4 6 h8 z" fill="green"><title>{}</title></path>"#,
+                    x(e.time),
+                    y + BAR,
+                    escape(e.to_string().trim_start())
+                );
+                let deadline = tasks
+                    .iter()
+                    .find(|t| t.id == e.task)
+                    .map_or(0, |t| t.deadline as u64);
+                if e.time + deadline <= horizon {
+                    let _ = writeln!(
+                        svg,
+                        r#"<line x1="{:.2}" y1="{}" x2="{:.2}" y2="{}" stroke="red" stroke-width="2"><title>{}#{} deadline {}</title></line>"#,
+                        x(e.time + deadline),
+                        y - 2.0,
+                        x(e.time + deadline),
+                        y + BAR + 2.0,
+                        escape(&e.task),
+                        e.job,
+                        e.time + deadline
+                    );
+                }
+            }
+            EventKind::DeadlineMiss => {
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{:.2}" y="{}" fill="red" text-anchor="middle">✗</text>"#,
+                    x(e.time),
+                    y + BAR + 10.0
+                );
+            }
+            _ => {}
+        }
+    }
+
+    // time axis
+    let y = tasks.len() as f64 * LANE;
+    let step = tick_step(horizon);
+    let mut t = 0;
+    while t <= horizon {
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{:.2}" y1="{}" x2="{:.2}" y2="{}" stroke="black"/><text x="{:.2}" y="{}" text-anchor="middle">{}</text>"#,
+            x(t),
+            y,
+            x(t),
+            y + 4.0,
+            x(t),
+            y + 16.0,
+            t
+        );
+        t += step;
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// A step of 1, 2 or 5 times a power of ten, giving at most 10 ticks
+fn tick_step(horizon: u64) -> u64 {
+    let mut step = 1;
+    loop {
+        for k in [1, 2, 5] {
+            if horizon / (step * k) <= 10 {
+                return step * k;
+            }
+        }
+        step *= 10;
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod gantt_tests {
+    use super::*;
+    use crate::tasks::tasks;
+
+    #[test]
+    fn test_gantt() {
+        let tasks = tasks();
+        let (ip, _) = pre_analysis(&tasks);
+        let config = SimConfig {
+            horizon: 200,
+            ..SimConfig::default()
+        };
+        let sim = simulate(&tasks, &ip, &config).unwrap();
+        let svg = gantt(&tasks, &sim);
+
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        // T2 holds R1 and R2 at 42..46, drawn as two nested bands
+        assert!(svg.contains("<title>T2#0 42..46 holding R1, R2</title>"));
+        assert_eq!(svg.matches("<path").count(), 2 + 1 + 4);
+        assert_eq!(tick_step(200), 20);
+        assert_eq!(tick_step(7), 1);
+        assert_eq!(tick_step(1234), 200);
+    }
+}
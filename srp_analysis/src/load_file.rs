@@ -0,0 +1,161 @@
+use crate::common::*;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Supported task set file formats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "toml" => Ok(Format::Toml),
+            _ => Err(format!("unknown format `{}`, expected `json` or `toml`", s)),
+        }
+    }
+}
+
+/// Errors raised while loading a task set
+#[derive(Debug)]
+pub enum LoadError {
+    Io(String, io::Error),
+    Json(String, serde_json::Error),
+    Toml(String, toml::de::Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(src, e) => write!(f, "{}: {}", src, e),
+            LoadError::Json(src, e) => write!(f, "{}: invalid JSON task set: {}", src, e),
+            LoadError::Toml(src, e) => write!(f, "{}: invalid TOML task set: {}", src, e),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+// A task set file, `{ "tasks": [...] }` in JSON or `[[tasks]]` in TOML
+#[derive(Deserialize)]
+struct TaskFile {
+    tasks: Tasks,
+}
+
+/// Loads a task set from `path`, where `-` denotes stdin.
+/// The format is taken from `format`, the file extension, or guessed from the content.
+pub fn load_file(path: &Path, format: Option<Format>) -> Result<Tasks, LoadError> {
+    let stdin = path == Path::new("-");
+    let src = if stdin {
+        "<stdin>".to_string()
+    } else {
+        path.display().to_string()
+    };
+
+    let content = if stdin {
+        let mut s = String::new();
+        io::stdin()
+            .read_to_string(&mut s)
+            .map_err(|e| LoadError::Io(src.clone(), e))?;
+        s
+    } else {
+        fs::read_to_string(path).map_err(|e| LoadError::Io(src.clone(), e))?
+    };
+
+    let format = format
+        .or_else(|| format_from_extension(path))
+        .unwrap_or_else(|| guess_format(&content));
+
+    parse(&content, format, src)
+}
+
+/// Parses a task set from a string in the given format
+pub fn parse_tasks(content: &str, format: Format) -> Result<Tasks, LoadError> {
+    parse(content, format, "<input>".to_string())
+}
+
+fn parse(content: &str, format: Format, src: String) -> Result<Tasks, LoadError> {
+    match format {
+        // JSON accepts either a bare array of tasks or a `TaskFile`
+        Format::Json if content.trim_start().starts_with('[') => {
+            serde_json::from_str::<Tasks>(content).map_err(|e| LoadError::Json(src, e))
+        }
+        Format::Json => serde_json::from_str::<TaskFile>(content)
+            .map(|file| file.tasks)
+            .map_err(|e| LoadError::Json(src, e)),
+        Format::Toml => toml::from_str::<TaskFile>(content)
+            .map(|file| file.tasks)
+            .map_err(|e| LoadError::Toml(src, e)),
+    }
+}
+
+fn format_from_extension(path: &Path) -> Option<Format> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| ext.parse().ok())
+}
+
+fn guess_format(content: &str) -> Format {
+    match content.trim_start().chars().next() {
+        Some('[') if !content.trim_start().starts_with("[[") => Format::Json,
+        Some('{') => Format::Json,
+        _ => Format::Toml,
+    }
+}
+
+#[cfg(test)]
+mod load_tests {
+    use super::*;
+
+    #[test]
+    fn test_json_and_toml() {
+        let json = r#"
+        [
+            {
+                "id": "T1", "prio": 1, "deadline": 100, "inter_arrival": 100,
+                "trace": { "id": "T1", "start": 0, "end": 10,
+                           "inner": [{ "id": "R1", "start": 2, "end": 4 }] }
+            }
+        ]"#;
+        let toml = r#"
+        [[tasks]]
+        id = "T1"
+        prio = 1
+        deadline = 100
+        inter_arrival = 100
+
+        [tasks.trace]
+        id = "T1"
+        start = 0
+        end = 10
+
+        [[tasks.trace.inner]]
+        id = "R1"
+        start = 2
+        end = 4
+        "#;
+
+        for (content, format) in &[(json, Format::Json), (toml, Format::Toml)] {
+            assert_eq!(guess_format(content), *format);
+            let tasks = parse_tasks(content, *format).unwrap();
+            assert_eq!(tasks.len(), 1);
+            assert_eq!(tasks[0].trace.inner[0].id, "R1");
+            assert_eq!(tasks[0].trace.inner[0].inner.len(), 0);
+        }
+    }
+
+    #[test]
+    fn test_missing_field() {
+        let json = r#"{ "tasks": [{ "id": "T1", "prio": 1, "inter_arrival": 100,
+                        "trace": { "id": "T1", "start": 0, "end": 10 } }] }"#;
+        let err = parse_tasks(json, Format::Json).unwrap_err();
+        assert!(err.to_string().contains("missing field `deadline`"));
+    }
+}
@@ -1,4 +1,5 @@
 #![allow(unused_imports, dead_code)]
+#![allow(clippy::needless_return, clippy::ptr_arg)]
 extern crate handlebars;
 extern crate serde;
 #[macro_use]
@@ -8,14 +9,22 @@ extern crate serde_json;
 
 mod cli;
 mod common;
+mod cross_check;
+mod edf;
+mod gantt;
+mod load_file;
+mod msrp;
+mod nvic;
+mod priority;
 mod render_file;
+mod rtic;
+mod sensitivity;
+mod simulation;
 mod srp_analysis;
+mod stack;
 mod tasks;
-
-use common::*;
-use tasks::*;
+mod trace_notation;
 
 fn main() {
-    let tasks = tasks();
-    cli::cli(&tasks);
+    cli::cli();
 }
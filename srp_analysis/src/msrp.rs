@@ -0,0 +1,222 @@
+use crate::common::*;
+use crate::srp_analysis::*;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+// Multiprocessor SRP (MSRP) analysis of a partitioned task set.
+//
+// Local resources are shared by tasks of a single core and handled by SRP.
+// Global resources are shared across cores, a task accessing a global
+// resource becomes non-preemptive and spins until the resource is free. In
+// the worst case it waits for the longest critical section of the resource
+// on each other core, `spin(r, k)` = sum over cores `k'` != `k` of the
+// longest critical section of `r` on `k'`.
+//
+// Each core is analysed by the uniprocessor analysis, with
+// - each global critical section prolonged by its spin time (inflating
+//   `C(t)` and the critical section lengths), and
+// - the ceiling of each global resource raised above all priorities, as a
+//   lower priority task in a global critical section (or spinning for one)
+//   blocks any task of its core.
+
+/// Analysis of the tasks of one core
+#[derive(Debug, Clone)]
+pub struct CoreAnalysis {
+    pub core: u32,
+    /// The task set of the core, with spin times added to the traces
+    pub tasks: Tasks,
+    pub analysis: Vec<TaskAnalysis>,
+    pub tot_util: Ratio,
+    pub verdict: Verdict,
+}
+
+/// Result of the MSRP analysis
+#[derive(Debug, Clone)]
+pub struct MsrpAnalysis {
+    pub cores: Vec<CoreAnalysis>,
+    /// The global resources, sorted
+    pub global: Vec<String>,
+    /// The spin time `spin(r, k)` of each global resource and core, sorted
+    pub spin: Vec<(String, u32, u64)>,
+}
+
+impl MsrpAnalysis {
+    /// The analysis of each task, in task set order
+    pub fn task_analysis(&self, tasks: &Tasks) -> Vec<TaskAnalysis> {
+        tasks
+            .iter()
+            .filter_map(|t| {
+                self.cores
+                    .iter()
+                    .flat_map(|c| c.analysis.iter())
+                    .find(|a| a.id == t.id)
+                    .cloned()
+            })
+            .collect()
+    }
+
+    /// The system is schedulable if each core is
+    pub fn verdict(&self) -> Verdict {
+        let failures: Vec<Failure> = self
+            .cores
+            .iter()
+            .flat_map(|c| c.verdict.failures.iter().cloned())
+            .collect();
+        Verdict {
+            schedulable: failures.is_empty(),
+            failures,
+        }
+    }
+}
+
+/// Returns the cores of the task set, sorted
+pub fn cores(tasks: &Tasks) -> Vec<u32> {
+    let mut cores: Vec<u32> = tasks.iter().map(|t| t.core).collect();
+    cores.sort();
+    cores.dedup();
+    cores
+}
+
+/// Analyses a partitioned task set under MSRP, the task set is assumed to
+/// pass `validate`. Fails if a trace inflated by the spin times exceeds the
+/// 32 bit timestamps.
+pub fn msrp_analysis(tasks: &Tasks, config: &Config) -> Result<MsrpAnalysis, String> {
+    let mut global: Vec<String> = global_resources(tasks).into_iter().collect();
+    global.sort();
+    let cores = cores(tasks);
+
+    let mut spin = vec![];
+    for r in &global {
+        for k in &cores {
+            let s = cores
+                .iter()
+                .filter(|other| *other != k)
+                .map(|other| {
+                    tasks
+                        .iter()
+                        .filter(|t| t.core == *other)
+                        .map(|t| wcet_resource(&t.trace, r))
+                        .max()
+                        .unwrap_or(0)
+                })
+                .sum::<u64>();
+            spin.push((r.clone(), *k, s));
+        }
+    }
+
+    let core_analysis = cores
+        .iter()
+        .map(|k| {
+            let spin: HashMap<&String, u64> = spin
+                .iter()
+                .filter(|(_, core, _)| core == k)
+                .map(|(r, _, s)| (r, *s))
+                .collect();
+            let tasks: Tasks = tasks
+                .iter()
+                .filter(|t| t.core == *k)
+                .map(|t| match inflate(&t.trace, &spin, &mut 0) {
+                    Some(trace) => Ok(Task { trace, ..t.clone() }),
+                    None => Err(format!(
+                        "task {}: the trace prolonged by the spin times overflows",
+                        t.id
+                    )),
+                })
+                .collect::<Result<_, _>>()?;
+
+            let (mut ip, tr) = pre_analysis(&tasks);
+            for r in &global {
+                if let Some(ceiling) = ip.get_mut(r) {
+                    *ceiling = u8::MAX;
+                }
+            }
+            let analysis = srp_analysis(&tasks, &ip, &tr, config);
+            let tot_util = tot_util_overheads(&tasks, &config.overheads);
+            let verdict = schedulability(tot_util, &analysis);
+            Ok(CoreAnalysis {
+                core: *k,
+                tasks,
+                analysis,
+                tot_util,
+                verdict,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(MsrpAnalysis {
+        cores: core_analysis,
+        global,
+        spin,
+    })
+}
+
+// Prolongs each global critical section by its spin time, at its start,
+// `shift` being the spin time added so far, `None` on overflow
+fn inflate(trace: &Trace, spin: &HashMap<&String, u64>, shift: &mut u32) -> Option<Trace> {
+    let start = trace.start.checked_add(*shift)?;
+    if let Some(s) = spin.get(&trace.id) {
+        *shift = shift.checked_add(u32::try_from(*s).ok()?)?;
+    }
+    let inner = trace
+        .inner
+        .iter()
+        .map(|i| inflate(i, spin, shift))
+        .collect::<Option<_>>()?;
+    Some(Trace {
+        id: trace.id.clone(),
+        start,
+        end: trace.end.checked_add(*shift)?,
+        inner,
+    })
+}
+
+#[cfg(test)]
+mod msrp_tests {
+    use super::*;
+
+    fn task(id: &str, prio: u8, core: u32, trace: &str) -> Task {
+        Task {
+            core,
+            ..test_task(id, prio, 100, trace)
+        }
+    }
+
+    #[test]
+    fn test_msrp() {
+        let tasks = vec![
+            task("H", 2, 0, "[H:0 [G:2 4] [R:5 6] 10]"),
+            task("L", 1, 0, "[L:0 [G:1 6] [R:7 8] 20]"),
+            task("X", 1, 1, "[X:0 [G:0 3] 5]"),
+        ];
+        let msrp = msrp_analysis(&tasks, &Config::default()).unwrap();
+        assert_eq!(msrp.global, vec!["G"]);
+        assert_eq!(
+            msrp.spin,
+            vec![("G".to_string(), 0, 3), ("G".to_string(), 1, 5)]
+        );
+        assert_eq!(
+            msrp.cores[0].tasks[0].trace.to_string(),
+            "[H:0 [G:2 7] [R:8 9] 13]"
+        );
+
+        // H is blocked by L spinning for and holding G, 5 + 3
+        let analysis = msrp.task_analysis(&tasks);
+        let rt: Vec<u64> = analysis.iter().map(|a| a.rt.unwrap()).collect();
+        assert_eq!(rt, vec![13 + 8, 23 + 13, 10]);
+        assert_eq!(msrp.cores[0].tot_util, Ratio::new(36, 100));
+        assert!(msrp.verdict().schedulable);
+    }
+
+    #[test]
+    fn test_spin_overflow() {
+        // X spins for the 4 * 10^9 cycles of Y holding G
+        let tasks = vec![
+            task("X", 1, 0, "[X:0 [G:1 2] 400000000]"),
+            task("Y", 1, 1, "[Y:0 [G:0 4000000000] 4000000000]"),
+        ];
+        assert_eq!(
+            msrp_analysis(&tasks, &Config::default()).unwrap_err(),
+            "task X: the trace prolonged by the spin times overflows"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use crate::common::*;
+use crate::priority::*;
+use std::fmt;
+
+// Mapping of logical priorities onto NVIC hardware priorities.
+//
+// The NVIC implements `bits` priority bits, the most significant bits of the
+// 8 bit priority field, where a lower value is a higher priority. As RTIC
+// does, logical priority `p` in 1..=2^`bits` is mapped to the hardware
+// priority `(2^bits - p) << (8 - bits)`, and a logical priority beyond the
+// number of levels is an error. Logical priority 0 (`#[idle]`) runs in thread
+// mode, below any interrupt, and has no hardware priority.
+//
+// The priority grouping splits the implemented bits into preemption priority
+// (the upper bits) and `sub_bits` bits of subpriority. Only the preemption
+// priority decides whether an interrupt preempts, the subpriority merely
+// orders pending interrupts of the same group, followed by the vector table
+// index. The effective priority of a task is thus the preemption group, 1
+// being the lowest, and resource ceilings follow by `pre_analysis` over the
+// effective priorities. The order within the group is given by the rank of
+// the task as its `vector`, to be analysed under `Policy::VectorOrder`. Tasks
+// the NVIC may serve in either order (of the same subpriority, without
+// distinct vectors) are given no rank, so that they both interfere with and
+// block each other.
+
+/// The priority configuration of the target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target {
+    /// Number of implemented priority bits, 1..=8
+    pub bits: u8,
+    /// Number of subpriority bits of the priority grouping, at most `bits`
+    pub sub_bits: u8,
+}
+
+impl Target {
+    pub fn new(bits: u8, sub_bits: u8) -> Result<Target, String> {
+        if !(1..=8).contains(&bits) {
+            return Err(format!("expected 1 to 8 priority bits, found {}", bits));
+        }
+        if sub_bits > bits {
+            return Err(format!(
+                "{} subpriority bits exceed the {} priority bits",
+                sub_bits, bits
+            ));
+        }
+        Ok(Target { bits, sub_bits })
+    }
+
+    /// The number of logical priority levels, 2^`bits`
+    pub fn levels(&self) -> u16 {
+        1 << self.bits
+    }
+
+    /// The number of preemption priority levels, 2^(`bits` - `sub_bits`)
+    pub fn groups(&self) -> u16 {
+        1 << (self.bits - self.sub_bits)
+    }
+
+    /// Returns the hardware priority of logical priority `prio`, `None` for
+    /// thread mode, an error beyond the levels of the target
+    pub fn hardware(&self, prio: u8) -> Result<Option<u8>, String> {
+        match prio as u16 {
+            0 => Ok(None),
+            prio if prio <= self.levels() => {
+                Ok(Some(((self.levels() - prio) << (8 - self.bits)) as u8))
+            }
+            _ => Err(format!(
+                "logical priority {} exceeds the {} levels of {} priority bits",
+                prio,
+                self.levels(),
+                self.bits
+            )),
+        }
+    }
+
+    /// Returns the effective priority of logical priority `prio`, the
+    /// preemption group of its hardware priority, 1 being the lowest and 0
+    /// for thread mode
+    pub fn effective(&self, prio: u8) -> Result<u8, String> {
+        Ok(match self.hardware(prio)? {
+            Some(hardware) => {
+                let group = hardware as u16 >> (8 - self.bits + self.sub_bits);
+                (self.groups() - group) as u8
+            }
+            None => 0,
+        })
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} priority bits, {} preemption levels",
+            self.bits,
+            self.groups()
+        )?;
+        if self.sub_bits > 0 {
+            write!(f, ", {} subpriority bits", self.sub_bits)?;
+        }
+        Ok(())
+    }
+}
+
+/// The mapping of a single task
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskMapping {
+    pub id: String,
+    pub logical: u8,
+    /// `None` for thread mode
+    pub hardware: Option<u8>,
+    pub effective: u8,
+    /// The rank within the preemption group, `None` if not ordered, see above
+    pub vector: Option<u32>,
+}
+
+/// Distinct logical priorities sharing an effective priority
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collision {
+    pub effective: u8,
+    /// The logical priorities, sorted
+    pub logical: Vec<u8>,
+    /// The tasks of these priorities, in task set order
+    pub tasks: Vec<String>,
+}
+
+impl fmt::Display for Collision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "logical priorities {} collapse onto priority {} ({})",
+            self.logical
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.effective,
+            self.tasks.join(", ")
+        )
+    }
+}
+
+/// Result of the priority mapping
+#[derive(Debug, Clone, PartialEq)]
+pub struct NvicMapping {
+    pub target: Target,
+    pub tasks: Vec<TaskMapping>,
+    pub collisions: Vec<Collision>,
+}
+
+impl NvicMapping {
+    /// The effective priority of each task
+    pub fn priorities(&self) -> Priorities {
+        self.tasks
+            .iter()
+            .map(|t| (t.id.clone(), t.effective))
+            .collect()
+    }
+
+    /// Returns the task set as scheduled by the NVIC, with the effective
+    /// priorities and the order within each preemption group
+    pub fn apply(&self, tasks: &Tasks) -> Tasks {
+        let mut tasks = apply_priorities(tasks, &self.priorities());
+        for t in &mut tasks {
+            if let Some(m) = self.tasks.iter().find(|m| m.id == t.id) {
+                t.vector = m.vector;
+            }
+        }
+        tasks
+    }
+}
+
+/// Maps the task priorities onto the target, an error if a priority exceeds
+/// the levels of the target
+pub fn nvic_mapping(tasks: &Tasks, target: Target) -> Result<NvicMapping, String> {
+    let mut mapped: Vec<TaskMapping> = tasks
+        .iter()
+        .map(|t| {
+            let error = |e: String| format!("task {}: {}", t.id, e);
+            Ok(TaskMapping {
+                id: t.id.clone(),
+                logical: t.prio,
+                hardware: target.hardware(t.prio).map_err(error)?,
+                effective: target.effective(t.prio).map_err(error)?,
+                vector: None,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+
+    // rank by hardware priority, then by vector, unless tied
+    let key = |i: usize| (mapped[i].hardware, tasks[i].vector.unwrap_or(u32::MAX));
+    let ranks: Vec<Option<u32>> = (0..mapped.len())
+        .map(|i| {
+            let peers: Vec<usize> = (0..mapped.len())
+                .filter(|j| *j != i && mapped[*j].effective == mapped[i].effective)
+                .collect();
+            let tied = peers.iter().any(|j| {
+                mapped[*j].hardware == mapped[i].hardware
+                    && match (tasks[i].vector, tasks[*j].vector) {
+                        (Some(a), Some(b)) => a == b,
+                        _ => true,
+                    }
+            });
+            if tied {
+                None
+            } else {
+                Some(peers.iter().filter(|j| key(**j) < key(i)).count() as u32)
+            }
+        })
+        .collect();
+    for (m, rank) in mapped.iter_mut().zip(ranks) {
+        m.vector = rank;
+    }
+
+    let mut effective: Vec<u8> = mapped.iter().map(|m| m.effective).collect();
+    effective.sort();
+    effective.dedup();
+    let collisions = effective
+        .into_iter()
+        .filter_map(|e| {
+            let group: Vec<&TaskMapping> = mapped.iter().filter(|m| m.effective == e).collect();
+            let mut logical: Vec<u8> = group.iter().map(|m| m.logical).collect();
+            logical.sort();
+            logical.dedup();
+            if logical.len() < 2 {
+                return None;
+            }
+            Some(Collision {
+                effective: e,
+                logical,
+                tasks: group.iter().map(|m| m.id.clone()).collect(),
+            })
+        })
+        .collect();
+
+    Ok(NvicMapping {
+        target,
+        tasks: mapped,
+        collisions,
+    })
+}
+
+#[cfg(test)]
+mod nvic_tests {
+    use super::*;
+    use crate::tasks::tasks;
+
+    #[test]
+    fn test_target() {
+        let target = Target::new(4, 0).unwrap();
+        assert_eq!(target.hardware(1), Ok(Some(0xF0)));
+        assert_eq!(target.hardware(16), Ok(Some(0x00)));
+        assert_eq!(target.hardware(0), Ok(None));
+        assert_eq!(
+            target.hardware(20),
+            Err("logical priority 20 exceeds the 16 levels of 4 priority bits".to_string())
+        );
+        assert_eq!(target.effective(3), Ok(3));
+        assert_eq!(target.effective(0), Ok(0));
+
+        // 4 preemption levels of 2 logical priorities each
+        let target = Target::new(3, 1).unwrap();
+        let effective: Vec<u8> = (1..=8).map(|p| target.effective(p).unwrap()).collect();
+        assert_eq!(effective, vec![1, 1, 2, 2, 3, 3, 4, 4]);
+        assert_eq!(
+            target.to_string(),
+            "3 priority bits, 4 preemption levels, 1 subpriority bits"
+        );
+
+        assert!(Target::new(0, 0).is_err());
+        assert!(Target::new(4, 5).is_err());
+    }
+
+    #[test]
+    fn test_mapping() {
+        let tasks = tasks();
+        assert!(nvic_mapping(&tasks, Target::new(4, 0).unwrap())
+            .unwrap()
+            .collisions
+            .is_empty());
+
+        // 2 levels are too few for priority 3
+        assert_eq!(
+            nvic_mapping(&tasks, Target::new(1, 0).unwrap()).unwrap_err(),
+            "task T3: logical priority 3 exceeds the 2 levels of 1 priority bits"
+        );
+
+        // with 2 preemption levels, T1 (1) and T2 (2) share the lowest one
+        let mapping = nvic_mapping(&tasks, Target::new(2, 1).unwrap()).unwrap();
+        assert_eq!(format_priorities(&mapping.priorities()), "T1=1, T2=1, T3=2");
+        assert_eq!(
+            mapping.collisions[0].to_string(),
+            "logical priorities 1, 2 collapse onto priority 1 (T1, T2)"
+        );
+
+        // R1 is used by T2 alone, its ceiling drops from 2 to 1
+        let mapped = mapping.apply(&tasks);
+        let (ip, _) = pre_analysis(&mapped);
+        assert_eq!((ip["R1"], ip["R2"]), (1, 2));
+        // T2 of the higher subpriority is served first
+        let vectors: Vec<Option<u32>> = mapped.iter().map(|t| t.vector).collect();
+        assert_eq!(vectors, vec![Some(1), Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn test_group_order() {
+        let tasks = vec![
+            test_task("A", 2, 100, "[A:0 10]"),
+            Task {
+                vector: Some(3),
+                ..test_task("B", 1, 100, "[B:0 10]")
+            },
+            Task {
+                vector: Some(5),
+                ..test_task("C", 1, 100, "[C:0 10]")
+            },
+            test_task("D", 3, 100, "[D:0 10]"),
+            test_task("E", 3, 100, "[E:0 10]"),
+        ];
+        // 2 preemption levels, A, B and C share the lower one
+        let mapping = nvic_mapping(&tasks, Target::new(2, 1).unwrap()).unwrap();
+        let vectors: Vec<Option<u32>> = mapping.tasks.iter().map(|t| t.vector).collect();
+        // B before C by vector table index, D and E in either order
+        assert_eq!(vectors, vec![Some(0), Some(1), Some(2), None, None]);
+    }
+}
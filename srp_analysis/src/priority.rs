@@ -0,0 +1,315 @@
+use crate::common::*;
+use crate::srp_analysis::*;
+use std::fmt;
+
+/// A priority for each task, in task set order
+pub type Priorities = Vec<(String, u8)>;
+
+/// Priority assignment algorithms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assignment {
+    /// Audsley's optimal priority assignment
+    Audsley,
+    /// Deadline monotonic, shorter `D(t)` gives higher priority
+    DeadlineMonotonic,
+    /// Rate monotonic, shorter `A(t)` gives higher priority
+    RateMonotonic,
+}
+
+impl fmt::Display for Assignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Assignment::Audsley => "Audsley",
+            Assignment::DeadlineMonotonic => "Deadline monotonic",
+            Assignment::RateMonotonic => "Rate monotonic",
+        })
+    }
+}
+
+impl std::str::FromStr for Assignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "audsley" => Ok(Assignment::Audsley),
+            "dm" => Ok(Assignment::DeadlineMonotonic),
+            "rm" => Ok(Assignment::RateMonotonic),
+            _ => Err(format!(
+                "unknown priority assignment `{}`, expected `audsley`, `dm` or `rm`",
+                s
+            )),
+        }
+    }
+}
+
+/// Proof that no feasible priority assignment exists: at priority `level`
+/// none of the remaining tasks meets its deadline when all other remaining
+/// tasks are given higher priority
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unassignable {
+    pub level: u8,
+    /// Tasks assigned to the levels below `level`
+    pub assigned: Priorities,
+    /// Each remaining task with its response time (or `J(t)` plus the
+    /// busy-period at which the recurrence exceeded the deadline) at `level`,
+    /// and deadline
+    pub candidates: Vec<(String, u64, u32)>,
+}
+
+impl fmt::Display for Unassignable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "no feasible priority assignment, no task can be given priority {}:",
+            self.level
+        )?;
+        for (id, rt, deadline) in &self.candidates {
+            write!(f, "\n  task {}: response time {} > {}", id, rt, deadline)?;
+        }
+        if !self.assigned.is_empty() {
+            write!(
+                f,
+                "\n  (assigned below: {})",
+                format_priorities(&self.assigned)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors of a priority assignment
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssignmentError {
+    /// More tasks than the assignment has distinct `u8` priorities for
+    TooManyTasks { tasks: usize, max: usize },
+    /// No feasible priority assignment exists
+    Unassignable(Unassignable),
+}
+
+impl fmt::Display for AssignmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssignmentError::TooManyTasks { tasks, max } => write!(
+                f,
+                "{} tasks exceed the {} distinct priorities of the assignment",
+                tasks, max
+            ),
+            AssignmentError::Unassignable(e) => e.fmt(f),
+        }
+    }
+}
+
+// Fails unless each of the tasks can be given a distinct priority up to `max`
+fn check_levels(tasks: &Tasks, max: u8) -> Result<(), AssignmentError> {
+    if tasks.len() > max as usize {
+        return Err(AssignmentError::TooManyTasks {
+            tasks: tasks.len(),
+            max: max as usize,
+        });
+    }
+    Ok(())
+}
+
+/// Returns the priorities in `T=P` form, e.g., `T1=1, T2=2`
+pub fn format_priorities(prios: &Priorities) -> String {
+    prios
+        .iter()
+        .map(|(id, prio)| format!("{}={}", id, prio))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the task set with priorities replaced
+pub fn apply_priorities(tasks: &Tasks, prios: &Priorities) -> Tasks {
+    let mut tasks = tasks.clone();
+    for t in &mut tasks {
+        if let Some((_, prio)) = prios.iter().find(|(id, _)| *id == t.id) {
+            t.prio = *prio;
+        }
+    }
+    tasks
+}
+
+/// Audsley's optimal priority assignment. Priorities 1, 2, ... are assigned
+/// from the lowest, each to the first remaining task that meets its deadline
+/// with all other remaining tasks at higher priority. Resource ceilings are
+/// recomputed by `pre_analysis` for each candidate.
+pub fn audsley(tasks: &Tasks, config: &Config) -> Result<Priorities, AssignmentError> {
+    // the remaining tasks are placed one level above the candidate
+    check_levels(tasks, u8::MAX - 1)?;
+
+    let mut assigned: Priorities = vec![];
+    let mut remaining: Vec<&Task> = tasks.iter().collect();
+
+    for level in 1..=tasks.len() as u8 {
+        let mut candidates = vec![];
+        let mut found = None;
+
+        for (i, c) in remaining.iter().enumerate() {
+            // the candidate at `level`, the other remaining tasks above it
+            let mut prios = assigned.clone();
+            for r in &remaining {
+                let prio = if r.id == c.id { level } else { level + 1 };
+                prios.push((r.id.clone(), prio));
+            }
+            let candidate_tasks = apply_priorities(tasks, &prios);
+            let (ip, tr) = pre_analysis(&candidate_tasks);
+            let task = candidate_tasks.iter().find(|t| t.id == c.id).unwrap();
+
+            // a deadline miss exceeds `D(t)` once `J(t)` is added
+            let rt = match response_time(task, &candidate_tasks, &ip, &tr, config) {
+                Ok(rt) => rt,
+                Err(miss) => task.jitter as u64 + miss.busy_period,
+            };
+            if rt <= task.deadline as u64 {
+                found = Some(i);
+                break;
+            }
+            candidates.push((c.id.clone(), rt, c.deadline));
+        }
+
+        match found {
+            Some(i) => {
+                let task = remaining.remove(i);
+                assigned.push((task.id.clone(), level));
+            }
+            None => {
+                return Err(AssignmentError::Unassignable(Unassignable {
+                    level,
+                    assigned,
+                    candidates,
+                }))
+            }
+        }
+    }
+
+    // report in task set order
+    Ok(tasks
+        .iter()
+        .map(|t| assigned.iter().find(|(id, _)| *id == t.id).unwrap().clone())
+        .collect())
+}
+
+/// Deadline monotonic priorities, see `monotonic`
+pub fn deadline_monotonic(tasks: &Tasks) -> Result<Priorities, AssignmentError> {
+    monotonic(tasks, |t| t.deadline)
+}
+
+/// Rate monotonic priorities, see `monotonic`
+pub fn rate_monotonic(tasks: &Tasks) -> Result<Priorities, AssignmentError> {
+    monotonic(tasks, |t| t.inter_arrival)
+}
+
+// Assigns distinct priorities 1, 2, ... by decreasing `key`, so the task with
+// the shortest `key` gets the highest priority. Ties are broken by task id,
+// the lexicographically smaller id getting the higher priority.
+fn monotonic(tasks: &Tasks, key: impl Fn(&Task) -> u32) -> Result<Priorities, AssignmentError> {
+    check_levels(tasks, u8::MAX)?;
+
+    let mut order: Vec<&Task> = tasks.iter().collect();
+    order.sort_by(|a, b| key(b).cmp(&key(a)).then_with(|| b.id.cmp(&a.id)));
+
+    Ok(tasks
+        .iter()
+        .map(|t| {
+            let level = order.iter().position(|o| o.id == t.id).unwrap() + 1;
+            (t.id.clone(), level as u8)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod priority_tests {
+    use super::*;
+
+    fn unassignable(r: Result<Priorities, AssignmentError>) -> Unassignable {
+        match r {
+            Err(AssignmentError::Unassignable(e)) => e,
+            r => panic!("expected no feasible assignment, found {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_audsley() {
+        // unschedulable with the given priorities, T1 misses its deadline
+        let tasks = vec![
+            test_task("T1", 1, 50, "[T1:0 [R:10 20] 30]"),
+            test_task("T2", 2, 200, "[T2:0 [R:10 15] 40]"),
+            test_task("T3", 3, 100, "[T3:0 10]"),
+        ];
+        let config = Config::default();
+        let prios = audsley(&tasks, &config).unwrap();
+        assert_eq!(format_priorities(&prios), "T1=2, T2=1, T3=3");
+
+        let tasks = apply_priorities(&tasks, &prios);
+        let (ip, tr) = pre_analysis(&tasks);
+        let analysis = srp_analysis(&tasks, &ip, &tr, &config);
+        assert!(schedulability(tot_util(&tasks), &analysis).schedulable);
+    }
+
+    #[test]
+    fn test_unassignable() {
+        let tasks = vec![
+            test_task("T1", 1, 30, "[T1:0 20]"),
+            test_task("T2", 2, 30, "[T2:0 20]"),
+        ];
+        let e = unassignable(audsley(&tasks, &Config::default()));
+        assert_eq!(e.level, 1);
+        assert_eq!(
+            e.candidates,
+            vec![("T1".to_string(), 40, 30), ("T2".to_string(), 40, 30)]
+        );
+    }
+
+    #[test]
+    fn test_unassignable_jitter() {
+        // A misses its deadline below B, as J + C = 50 + 60 > 100 already
+        let mut tasks = vec![
+            test_task("A", 1, 100, "[A:0 60]"),
+            test_task("B", 2, 100, "[B:0 10]"),
+        ];
+        tasks[0].jitter = 50;
+        let e = unassignable(audsley(&tasks, &Config::default()));
+        assert_eq!(e.level, 1);
+        assert_eq!(
+            e.candidates,
+            vec![("A".to_string(), 50 + 60, 100), ("B".to_string(), 130, 100)]
+        );
+    }
+
+    #[test]
+    fn test_too_many_tasks() {
+        let tasks: Tasks = (0..255)
+            .map(|n| test_task(&format!("T{}", n), 1, 1000, "[T:0 1]"))
+            .collect();
+        assert_eq!(
+            audsley(&tasks, &Config::default()),
+            Err(AssignmentError::TooManyTasks {
+                tasks: 255,
+                max: 254
+            })
+        );
+        assert!(deadline_monotonic(&tasks).is_ok());
+    }
+
+    #[test]
+    fn test_monotonic() {
+        let tasks = vec![
+            test_task("T1", 1, 100, "[T1:0 10]"),
+            test_task("T2", 2, 200, "[T2:0 10]"),
+            test_task("T3", 3, 50, "[T3:0 10]"),
+            test_task("T0", 3, 100, "[T0:0 10]"),
+        ];
+        assert_eq!(
+            format_priorities(&deadline_monotonic(&tasks).unwrap()),
+            "T1=2, T2=1, T3=4, T0=3"
+        );
+
+        let mut tasks = tasks;
+        tasks[1].inter_arrival = 10;
+        assert_eq!(
+            format_priorities(&rate_monotonic(&tasks).unwrap()),
+            "T1=1, T2=4, T3=3, T0=2"
+        );
+    }
+}
@@ -11,13 +11,70 @@ use handlebars::{
 
 use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 
+use crate::common::*;
+use crate::cross_check::*;
+use crate::edf::*;
+use crate::gantt::*;
+use crate::msrp::*;
+use crate::nvic::*;
+use crate::priority::*;
+use crate::sensitivity::*;
+use crate::simulation::*;
+use crate::srp_analysis::*;
+use crate::stack::*;
+
 #[derive(Serialize)]
 pub struct Res {
     id: String,
-    rt: f32,
-    wcet: f32,
-    bt: f32,
-    pre: f32,
+    rt: Option<u64>,
+    wcet: u64,
+    bt: u64,
+    // the section causing `bt`, e.g. `T2 holding R2 at 12..16`
+    blocker: Option<String>,
+    pre: Option<u64>,
+    // the interference of each preempting task, e.g. `T3: 2 × 30 = 60`
+    interference: Vec<String>,
+    // worst-case response jitter, `R(t)` - `C(t)`
+    jitter: Option<u64>,
+    // the part of `rt` due to scheduling overheads
+    overhead: Option<u64>,
+    deadline: u32,
+    // busy-period at which the deadline was exceeded, plus `release_jitter`
+    miss: Option<u64>,
+    release_jitter: u32,
+    derivation: String,
+}
+
+// Formats an iteration of the recurrence, e.g.
+// `R_1 = 10 + 0 + ceil(10/200) * 30 + ceil(10/50) * 30 = 70`,
+// where release jitter is shown as `ceil((10 + J)/200)`
+fn format_iteration(s: usize, i: &Iteration, a: &TaskAnalysis) -> String {
+    let mut line = format!("R_{} = {} + {}", s, a.wcet, a.bt);
+    for t in &i.terms {
+        if t.jitter > 0 {
+            line += &format!(
+                " + ceil(({} + {})/{}) * {}",
+                t.prev, t.jitter, t.inter_arrival, t.wcet
+            );
+        } else {
+            line += &format!(" + ceil({}/{}) * {}", t.prev, t.inter_arrival, t.wcet);
+        }
+    }
+    let jitter = a.jitter as u64;
+    if jitter > 0 && jitter + i.value > a.deadline as u64 {
+        format!(
+            "{} = {}, J + {} = {} > D = {}",
+            line,
+            i.value,
+            i.value,
+            jitter + i.value,
+            a.deadline
+        )
+    } else if i.value > a.deadline as u64 {
+        format!("{} = {} > D = {}", line, i.value, a.deadline)
+    } else {
+        format!("{} = {}", line, i.value)
+    }
 }
 
 fn format_helper(
@@ -36,8 +93,9 @@ fn format_helper(
 }
 
 pub fn make_data(
-    tot_util: &f32,
-    analysis: &Vec<(String, f32, f32, f32, f32)>,
+    tot_util: &Ratio,
+    analysis: &Vec<TaskAnalysis>,
+    verdict: &Verdict,
 ) -> Map<String, Json> {
     let mut data = Map::new();
     let dt = &chrono::offset::Utc::now().to_rfc2822();
@@ -45,37 +103,525 @@ pub fn make_data(
 
     for i in analysis {
         let res = Res {
-            id: i.0.clone(),
-            rt: i.1.clone(),
-            wcet: i.2.clone(),
-            bt: i.3.clone(),
-            pre: i.4.clone(),
+            id: i.id.clone(),
+            rt: i.rt.ok(),
+            wcet: i.wcet,
+            bt: i.bt,
+            blocker: i.blocker.as_ref().map(|b| b.to_string()),
+            pre: i.pre.ok(),
+            interference: i
+                .interference
+                .iter()
+                .map(|t| {
+                    format!(
+                        "{}: {} × {} = {}",
+                        t.task,
+                        t.preemptions(),
+                        t.wcet,
+                        t.cycles()
+                    )
+                })
+                .collect(),
+            jitter: i.rt.ok().map(|rt| rt - i.wcet),
+            overhead: i.overhead,
+            deadline: i.deadline,
+            miss: i.rt.err().map(|miss| i.jitter as u64 + miss.busy_period),
+            release_jitter: i.jitter,
+            derivation: i
+                .iterations
+                .iter()
+                .enumerate()
+                .map(|(s, it)| format_iteration(s, it, i))
+                .chain(match (i.jitter, i.rt) {
+                    (j, Ok(rt)) if j > 0 && !i.iterations.is_empty() => {
+                        Some(format!("R = J + R_{} = {}", i.iterations.len() - 1, rt))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
         };
 
         result.push(res);
     }
 
-    data.insert("date".to_string(), to_json(&dt.to_string()[0..25]));
+    data.insert("date".to_string(), to_json(&dt[0..25]));
     data.insert("teams".to_string(), to_json(&result));
-    data.insert("load".to_string(), to_json(&tot_util.to_string()));
+    data.insert("load".to_string(), to_json(tot_util.to_string()));
+    data.insert("schedulable".to_string(), to_json(verdict.schedulable));
+    data.insert(
+        "failures".to_string(),
+        to_json(
+            verdict
+                .failures
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>(),
+        ),
+    );
     data
 }
 
-pub fn render_file(
-    tot_util: &f32,
-    analysis: &Vec<(String, f32, f32, f32, f32)>,
-) -> Result<(), Box<dyn Error>> {
+#[derive(Serialize)]
+pub struct EdfRes {
+    id: String,
+    deadline: u32,
+    level: u8,
+    wcet: u64,
+    bt: u64,
+    blocker: Option<String>,
+}
+
+// Preemption levels, blocking and the processor demand test under EDF
+pub fn edf_data(edf: &EdfAnalysis) -> Json {
+    let tasks: Vec<EdfRes> = edf
+        .tasks
+        .iter()
+        .map(|t| EdfRes {
+            id: t.id.clone(),
+            deadline: t.deadline,
+            level: t.level,
+            wcet: t.wcet,
+            bt: t.bt,
+            blocker: t.blocker.as_ref().map(|b| b.to_string()),
+        })
+        .collect();
+
+    let mut data = Map::new();
+    data.insert("tasks".to_string(), to_json(&tasks));
+    if let Some(busy_period) = edf.busy_period {
+        data.insert("busy_period".to_string(), to_json(busy_period));
+        data.insert("has_busy_period".to_string(), to_json(true));
+    }
+    data.insert("points".to_string(), to_json(edf.points));
+    if let Some(miss) = edf.miss {
+        data.insert(
+            "miss".to_string(),
+            to_json(format!(
+                "demand {} (including blocking {}) > L = {}",
+                miss.demand, miss.blocking, miss.interval
+            )),
+        );
+    }
+    Json::Object(data)
+}
+
+#[derive(Serialize)]
+pub struct CoreRes {
+    core: u32,
+    tasks: String,
+    load: String,
+    schedulable: bool,
+}
+
+#[derive(Serialize)]
+pub struct SpinRes {
+    resource: String,
+    core: u32,
+    spin: u64,
+}
+
+// Per core load and the spin times of the global resources under MSRP
+pub fn msrp_data(msrp: &MsrpAnalysis) -> Json {
+    let cores: Vec<CoreRes> = msrp
+        .cores
+        .iter()
+        .map(|c| CoreRes {
+            core: c.core,
+            tasks: c
+                .tasks
+                .iter()
+                .map(|t| t.id.clone())
+                .collect::<Vec<_>>()
+                .join(", "),
+            load: c.tot_util.to_string(),
+            schedulable: c.verdict.schedulable,
+        })
+        .collect();
+    let spin: Vec<SpinRes> = msrp
+        .spin
+        .iter()
+        .map(|(resource, core, spin)| SpinRes {
+            resource: resource.clone(),
+            core: *core,
+            spin: *spin,
+        })
+        .collect();
+
+    let mut data = Map::new();
+    data.insert("cores".to_string(), to_json(&cores));
+    data.insert("spin".to_string(), to_json(&spin));
+    Json::Object(data)
+}
+
+#[derive(Serialize)]
+pub struct PrioChange {
+    id: String,
+    before: u8,
+    after: u8,
+    changed: bool,
+}
+
+// Describes the scheduling overheads, e.g. `preemption 12, lock 2, unlock 2, dispatch 6`
+pub fn overheads_data(overheads: &Overheads) -> Json {
+    to_json(format!(
+        "preemption {}, lock {}, unlock {}, dispatch {}",
+        overheads.preemption, overheads.lock, overheads.unlock, overheads.dispatch
+    ))
+}
+
+// Compares the priorities and resource ceilings of the input task set with
+// those after priority assignment
+pub fn priority_data(assignment: Assignment, before: &Tasks, after: &Tasks) -> Json {
+    let (tasks, resources) = prio_changes(before, after);
+
+    let mut data = Map::new();
+    data.insert("method".to_string(), to_json(assignment.to_string()));
+    data.insert("tasks".to_string(), to_json(&tasks));
+    data.insert("resources".to_string(), to_json(&resources));
+    Json::Object(data)
+}
+
+#[derive(Serialize)]
+pub struct CandidateRes {
+    id: String,
+    rt: u64,
+    deadline: u32,
+}
+
+// The proof that no feasible priority assignment exists
+pub fn unassignable_data(e: &Unassignable) -> Json {
+    let candidates: Vec<CandidateRes> = e
+        .candidates
+        .iter()
+        .map(|(id, rt, deadline)| CandidateRes {
+            id: id.clone(),
+            rt: *rt,
+            deadline: *deadline,
+        })
+        .collect();
+
+    let mut data = Map::new();
+    data.insert("level".to_string(), to_json(e.level));
+    data.insert(
+        "assigned".to_string(),
+        to_json(format_priorities(&e.assigned)),
+    );
+    data.insert("candidates".to_string(), to_json(&candidates));
+    Json::Object(data)
+}
+
+// The priority of each task and the ceiling of each resource, before and after
+fn prio_changes(before: &Tasks, after: &Tasks) -> (Vec<PrioChange>, Vec<PrioChange>) {
+    let (ip_before, _) = pre_analysis(before);
+    let (ip_after, _) = pre_analysis(after);
+
+    let change = |id: &String, before: u8, after: u8| PrioChange {
+        id: id.clone(),
+        before,
+        after,
+        changed: before != after,
+    };
+
+    let tasks: Vec<PrioChange> = before
+        .iter()
+        .zip(after)
+        .map(|(b, a)| change(&b.id, b.prio, a.prio))
+        .collect();
+    let mut resources: Vec<&String> = ip_before
+        .keys()
+        .filter(|id| !before.iter().any(|t| t.trace.id == **id))
+        .collect();
+    resources.sort();
+    let resources: Vec<PrioChange> = resources
+        .into_iter()
+        .map(|id| change(id, ip_before[id], ip_after[id]))
+        .collect();
+    (tasks, resources)
+}
+
+#[derive(Serialize)]
+pub struct NvicRes {
+    id: String,
+    logical: u8,
+    // e.g. `0xE0`, `-` for thread mode
+    hardware: String,
+    effective: u8,
+    // shares its effective priority with another logical priority
+    collided: bool,
+}
+
+// The NVIC priority of each task, the resource ceilings before and after the
+// mapping, and the collapsed priorities
+pub fn nvic_data(mapping: &NvicMapping, before: &Tasks, after: &Tasks) -> Json {
+    let tasks: Vec<NvicRes> = mapping
+        .tasks
+        .iter()
+        .map(|t| NvicRes {
+            id: t.id.clone(),
+            logical: t.logical,
+            hardware: t
+                .hardware
+                .map_or("-".to_string(), |h| format!("{:#04X}", h)),
+            effective: t.effective,
+            collided: mapping.collisions.iter().any(|c| c.tasks.contains(&t.id)),
+        })
+        .collect();
+    let (_, resources) = prio_changes(before, after);
+
+    let mut data = Map::new();
+    data.insert("target".to_string(), to_json(mapping.target.to_string()));
+    data.insert("tasks".to_string(), to_json(&tasks));
+    data.insert("resources".to_string(), to_json(&resources));
+    data.insert(
+        "collisions".to_string(),
+        to_json(
+            mapping
+                .collisions
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>(),
+        ),
+    );
+    Json::Object(data)
+}
+
+#[derive(Serialize)]
+pub struct ResourceUse {
+    task: String,
+    prio: u8,
+    cs: u64,
+}
+
+#[derive(Serialize)]
+pub struct ResourceRes {
+    id: String,
+    ceiling: u8,
+    users: Vec<ResourceUse>,
+}
+
+#[derive(Serialize)]
+pub struct AccessRow {
+    id: String,
+    prio: u8,
+    // the longest critical section of each resource, in column order,
+    // empty if not accessed
+    cells: Vec<String>,
+}
+
+// Resource ceilings, the tasks accessing each resource, and the task x
+// resource access matrix, from the maps of `pre_analysis`
+pub fn resource_data(tasks: &Tasks, ip: &IdPrio, tr: &TaskResources) -> Json {
+    let mut resources: Vec<&String> = ip
+        .keys()
+        .filter(|id| !tasks.iter().any(|t| t.trace.id == **id))
+        .collect();
+    resources.sort();
+
+    let uses = |t: &Task, r: &String| tr.get(&t.id).is_some_and(|rs| rs.contains(r));
+    let table: Vec<ResourceRes> = resources
+        .iter()
+        .map(|r| ResourceRes {
+            id: r.to_string(),
+            ceiling: ip[*r],
+            users: tasks
+                .iter()
+                .filter(|t| uses(t, r))
+                .map(|t| ResourceUse {
+                    task: t.id.clone(),
+                    prio: t.prio,
+                    cs: wcet_resource(&t.trace, r),
+                })
+                .collect(),
+        })
+        .collect();
+    let matrix: Vec<AccessRow> = tasks
+        .iter()
+        .map(|t| AccessRow {
+            id: t.id.clone(),
+            prio: t.prio,
+            cells: resources
+                .iter()
+                .map(|r| {
+                    if uses(t, r) {
+                        wcet_resource(&t.trace, r).to_string()
+                    } else {
+                        String::new()
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+
+    let mut data = Map::new();
+    data.insert("names".to_string(), to_json(&resources));
+    data.insert("table".to_string(), to_json(&table));
+    data.insert("matrix".to_string(), to_json(&matrix));
+    Json::Object(data)
+}
+
+#[derive(Serialize)]
+pub struct StackRes {
+    id: String,
+    stack: Option<u32>,
+    // Handlebars treats 0 as false, the flags tell a 0 from a missing value
+    has_stack: bool,
+    threshold: u8,
+    depth: u64,
+    chain: String,
+}
+
+// Per task stack usage and the worst-case depth of the shared stack
+pub fn stack_data(stack: &StackAnalysis) -> Json {
+    let tasks: Vec<StackRes> = stack
+        .tasks
+        .iter()
+        .map(|t| StackRes {
+            id: t.id.clone(),
+            stack: t.stack,
+            has_stack: t.stack.is_some(),
+            threshold: t.threshold,
+            depth: t.depth,
+            chain: t.chain.join(" → "),
+        })
+        .collect();
+
+    let mut data = Map::new();
+    data.insert("tasks".to_string(), to_json(&tasks));
+    data.insert("depth".to_string(), to_json(stack.depth));
+    data.insert("chain".to_string(), to_json(stack.chain.join(" → ")));
+    Json::Object(data)
+}
+
+#[derive(Serialize)]
+pub struct SensitivityRes {
+    id: String,
+    wcet: u64,
+    schedulable: bool,
+    max_wcet: Option<u64>,
+    headroom: Option<u64>,
+    percent: Option<String>,
+    inter_arrival: u32,
+    min_inter_arrival: Option<u32>,
+    has_min_inter_arrival: bool,
+}
+
+// Per task WCET headroom and minimum inter-arrival time, and the critical
+// scaling factor
+pub fn sensitivity_data(sensitivity: &Sensitivity) -> Json {
+    let tasks: Vec<SensitivityRes> = sensitivity
+        .tasks
+        .iter()
+        .map(|t| SensitivityRes {
+            id: t.id.clone(),
+            wcet: t.wcet,
+            schedulable: t.max_wcet.is_some(),
+            max_wcet: t.max_wcet,
+            headroom: t.headroom(),
+            percent: t.headroom_percent().map(|p| format!("{:.1}", p)),
+            inter_arrival: t.inter_arrival,
+            min_inter_arrival: t.min_inter_arrival,
+            has_min_inter_arrival: t.min_inter_arrival.is_some(),
+        })
+        .collect();
+
+    let mut data = Map::new();
+    data.insert("tasks".to_string(), to_json(&tasks));
+    if let Some(k) = sensitivity.scaling {
+        data.insert(
+            "scaling".to_string(),
+            to_json(format!(
+                "{:.3} ({:+.1}%)",
+                k as f64 / 1000.0,
+                (k as f64 - 1000.0) / 10.0
+            )),
+        );
+    }
+    Json::Object(data)
+}
+
+#[derive(Serialize)]
+pub struct SimulationRes {
+    id: String,
+    released: u64,
+    completed: u64,
+    max_rt: Option<u64>,
+    has_max_rt: bool,
+    misses: u64,
+}
+
+// Observed response times, the job event log and the Gantt chart of a
+// simulation
+pub fn simulation_data(tasks: &Tasks, simulation: &Simulation) -> Json {
+    let observed: Vec<SimulationRes> = simulation
+        .tasks
+        .iter()
+        .map(|t| SimulationRes {
+            id: t.id.clone(),
+            released: t.released,
+            completed: t.completed,
+            max_rt: t.max_rt,
+            has_max_rt: t.max_rt.is_some(),
+            misses: t.misses,
+        })
+        .collect();
+
+    let mut data = Map::new();
+    data.insert("horizon".to_string(), to_json(simulation.horizon));
+    data.insert("gantt".to_string(), to_json(gantt(tasks, simulation)));
+    data.insert("tasks".to_string(), to_json(&observed));
+    data.insert(
+        "events".to_string(),
+        to_json(
+            simulation
+                .events
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+    );
+    Json::Object(data)
+}
+
+#[derive(Serialize)]
+pub struct CheckRes {
+    id: String,
+    bound: Option<u64>,
+    has_bound: bool,
+    observed: Option<u64>,
+    has_observed: bool,
+    scenario: Option<String>,
+    violated: bool,
+}
+
+// Analytic bounds against simulated response times
+pub fn cross_check_data(checks: &Vec<TaskCheck>) -> Json {
+    let tasks: Vec<CheckRes> = checks
+        .iter()
+        .map(|c| CheckRes {
+            id: c.id.clone(),
+            bound: c.bound.ok(),
+            has_bound: c.bound.is_ok(),
+            observed: c.observed,
+            has_observed: c.observed.is_some(),
+            scenario: c.scenario.clone(),
+            violated: c.violated(),
+        })
+        .collect();
+    to_json(&tasks)
+}
+
+pub fn render_file(data: &Map<String, Json>) -> Result<(), Box<dyn Error>> {
     let mut handlebars = Handlebars::new();
     handlebars.register_helper("format", Box::new(format_helper));
 
-    let data = make_data(&tot_util, &analysis);
-
     handlebars
         .register_template_file("template", "./render_file/template.hbs")
         .unwrap();
 
     let mut output_file = File::create("target/srp_analysis.html")?;
-    handlebars.render_to_write("template", &data, &mut output_file)?;
+    handlebars.render_to_write("template", data, &mut output_file)?;
     println!("target/srp_analysis.html generated");
     Ok(())
 }
@@ -0,0 +1,403 @@
+use crate::common::*;
+use std::fmt;
+use std::fmt::Write;
+
+// Import of the task and resource structure of an RTIC application.
+//
+// The source is scanned (not compiled) for the `#[task(...)]` attributes of
+// the `#[app]` module, each followed by the `fn` of the task. Of the
+// attribute arguments
+// - `binds = INTERRUPT` names the interrupt of a hardware task,
+// - `priority = N` gives the logical priority, 1 if omitted, and
+// - `resources = [..]` (RTIC 0.5) or `shared = [..]` (RTIC 1) lists the
+//   shared resources, `&` and `&mut` prefixes are ignored,
+// all others (`local`, `capacity`, `spawn`, ...) are ignored. `#[idle]` is
+// imported as a task of priority 0, its critical sections raise the system
+// ceiling and so block the tasks sharing its resources. `#[init]` runs before
+// any task and is skipped.
+//
+// The result is a skeleton task set, each task accessing each of its
+// resources by a zero-length critical section. Timing (deadlines,
+// inter-arrival times and the traces) is left to be filled in from
+// measurements.
+
+/// A task of an RTIC application
+#[derive(Debug, Clone, PartialEq)]
+pub struct RticTask {
+    pub id: String,
+    /// The bound interrupt, `None` for a software task
+    pub binds: Option<String>,
+    pub prio: u8,
+    /// The shared resources, in declaration order
+    pub resources: Vec<String>,
+    /// True for the `#[idle]` function
+    pub idle: bool,
+}
+
+/// Errors raised while scanning an RTIC application
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    /// No `#[app]` attribute found
+    NoApp,
+    /// A `#[task]` attribute at `line` that could not be read
+    Task { line: usize, msg: String },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::NoApp => write!(f, "no RTIC `#[app]` found"),
+            ImportError::Task { line, msg } => write!(f, "line {}: {}", line, msg),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Scans the source of an RTIC application for its tasks
+pub fn scan_app(src: &str) -> Result<Vec<RticTask>, ImportError> {
+    let src = strip_comments(src);
+    if !src.contains("#[app") && !src.contains("::app(") {
+        return Err(ImportError::NoApp);
+    }
+
+    let mut tasks = vec![];
+    let mut rest = 0;
+    while let Some((at, idle)) = next_attribute(&src[rest..]) {
+        let at = rest + at;
+        let line = src[..at].matches('\n').count() + 1;
+        let error = |msg: &str| ImportError::Task {
+            line,
+            msg: msg.to_string(),
+        };
+
+        // the arguments, if any, and the end of the attribute
+        // `#[task` and `#[idle` are of equal length
+        let after = at + "#[task".len();
+        let (args, end) = match src[after..].trim_start().strip_prefix('(') {
+            Some(_) => {
+                let open = after + src[after..].find('(').unwrap();
+                let close = matching(&src, open).ok_or_else(|| error("unclosed attribute"))?;
+                (&src[open + 1..close], close + 1)
+            }
+            None => ("", after),
+        };
+        rest = end;
+
+        let id = src[end..]
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .skip_while(|w| *w != "fn")
+            .skip(1)
+            .find(|w| !w.is_empty())
+            .ok_or_else(|| error("no `fn` follows the attribute"))?;
+
+        let mut task = RticTask {
+            id: id.to_string(),
+            binds: None,
+            prio: if idle { 0 } else { 1 },
+            resources: vec![],
+            idle,
+        };
+        for arg in split_top_level(args) {
+            let (key, value) = match arg.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            match key {
+                "binds" => task.binds = Some(value.to_string()),
+                "priority" => {
+                    task.prio = value
+                        .parse()
+                        .map_err(|_| error(&format!("invalid priority `{}`", value)))?
+                }
+                "resources" | "shared" => {
+                    let list = value
+                        .strip_prefix('[')
+                        .and_then(|v| v.strip_suffix(']'))
+                        .ok_or_else(|| error(&format!("expected a list of {}", key)))?;
+                    task.resources = split_top_level(list)
+                        .into_iter()
+                        .map(|r| {
+                            r.trim_start_matches('&')
+                                .trim_start()
+                                .trim_start_matches("mut ")
+                                .trim()
+                                .to_string()
+                        })
+                        .filter(|r| !r.is_empty())
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+/// Returns the skeleton task set, see above
+pub fn skeleton(rtic: &[RticTask]) -> Tasks {
+    rtic.iter()
+        .map(|t| Task {
+            id: t.id.clone(),
+            prio: t.prio,
+            deadline: 0,
+            inter_arrival: 0,
+            vector: None,
+            jitter: 0,
+            stack: None,
+            core: 0,
+            trace: Trace {
+                id: t.id.clone(),
+                start: 0,
+                end: 0,
+                inner: t
+                    .resources
+                    .iter()
+                    .map(|r| Trace {
+                        id: r.clone(),
+                        start: 0,
+                        end: 0,
+                        inner: vec![],
+                    })
+                    .collect(),
+            },
+        })
+        .collect()
+}
+
+/// Returns the skeleton task set as a TOML task set file, with traces in
+/// bracket notation, imported from `src`
+pub fn skeleton_toml(rtic: &[RticTask], src: &str) -> String {
+    let mut toml = String::new();
+    let _ = writeln!(toml, "# Task set imported from {}", src);
+    let _ = writeln!(
+        toml,
+        "# Fill in deadline, inter_arrival and the trace timings from measurements"
+    );
+    for (r, t) in rtic.iter().zip(skeleton(rtic)) {
+        let _ = writeln!(toml);
+        let _ = writeln!(toml, "[[tasks]]");
+        let _ = writeln!(toml, "id = \"{}\"", t.id);
+        match &r.binds {
+            Some(binds) => {
+                let _ = writeln!(toml, "# binds = {}", binds);
+            }
+            None if r.idle => {
+                let _ = writeln!(toml, "# idle");
+            }
+            None => {
+                let _ = writeln!(toml, "# software task");
+            }
+        }
+        let _ = writeln!(toml, "prio = {}", t.prio);
+        let _ = writeln!(toml, "deadline = {}", t.deadline);
+        let _ = writeln!(toml, "inter_arrival = {}", t.inter_arrival);
+        let _ = writeln!(toml, "trace = \"{}\"", t.trace);
+    }
+    toml
+}
+
+// Returns the position of the next `#[task` or `#[idle` attribute, true for idle
+fn next_attribute(src: &str) -> Option<(usize, bool)> {
+    match (src.find("#[task"), src.find("#[idle")) {
+        (Some(t), Some(i)) if i < t => Some((i, true)),
+        (Some(t), _) => Some((t, false)),
+        (None, Some(i)) => Some((i, true)),
+        (None, None) => None,
+    }
+}
+
+// Removes `//` and `/* */` comments, keeping line breaks and string literals
+fn strip_comments(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('"', _) => {
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    match c {
+                        '\\' => out.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                    }
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Returns the index of the `)` matching the `(` at `open`
+fn matching(src: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in src[open..].char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Splits at the commas outside of brackets
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts.into_iter().filter(|p| !p.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod rtic_tests {
+    use super::*;
+    use crate::load_file::*;
+
+    const APP: &str = r#"
+#[rtic::app(device = stm32f4::stm32f411, dispatchers = [EXTI1])]
+mod app {
+    #[shared]
+    struct Shared {
+        a: u32,
+        b: u32,
+    }
+
+    #[init]
+    fn init(_: init::Context) -> (Shared, Local, init::Monotonics) { todo!() }
+
+    // #[task(binds = EXTI2, priority = 9)] commented out
+    #[task(binds = EXTI0, priority = 1, shared = [a], local = [x: u32 = 0])]
+    fn t1(cx: t1::Context) {}
+
+    #[task(
+        binds = EXTI3,
+        priority = 2,
+        shared = [a, b],
+    )]
+    fn t2(cx: t2::Context) {}
+
+    #[task(shared = [&b], capacity = 4)]
+    fn t3(cx: t3::Context) {}
+
+    #[idle(shared = [b])]
+    fn idle(cx: idle::Context) -> ! {
+        loop {}
+    }
+}
+"#;
+
+    #[test]
+    fn test_scan() {
+        let tasks = scan_app(APP).unwrap();
+        assert_eq!(
+            tasks,
+            vec![
+                RticTask {
+                    id: "t1".to_string(),
+                    binds: Some("EXTI0".to_string()),
+                    prio: 1,
+                    resources: vec!["a".to_string()],
+                    idle: false,
+                },
+                RticTask {
+                    id: "t2".to_string(),
+                    binds: Some("EXTI3".to_string()),
+                    prio: 2,
+                    resources: vec!["a".to_string(), "b".to_string()],
+                    idle: false,
+                },
+                RticTask {
+                    id: "t3".to_string(),
+                    binds: None,
+                    prio: 1,
+                    resources: vec!["b".to_string()],
+                    idle: false,
+                },
+                RticTask {
+                    id: "idle".to_string(),
+                    binds: None,
+                    prio: 0,
+                    resources: vec!["b".to_string()],
+                    idle: true,
+                },
+            ]
+        );
+
+        // RTIC 0.5 resources, with `&` and `&mut` prefixes
+        let tasks = scan_app(
+            "#[app(device = x)] const APP: () = { \
+             #[task(binds = USART1, priority = 3, resources = [&a, &mut b])] \
+             fn usart1(cx: usart1::Context) {} };",
+        )
+        .unwrap();
+        assert_eq!(tasks[0].prio, 3);
+        assert_eq!(tasks[0].resources, vec!["a", "b"]);
+
+        assert_eq!(scan_app("fn main() {}"), Err(ImportError::NoApp));
+        assert_eq!(
+            scan_app("#[app]\n\n#[task(priority = high)]\nfn t() {}")
+                .unwrap_err()
+                .to_string(),
+            "line 3: invalid priority `high`"
+        );
+    }
+
+    #[test]
+    fn test_skeleton() {
+        let rtic = scan_app(APP).unwrap();
+        let toml = skeleton_toml(&rtic, "app.rs");
+        assert!(toml.contains("id = \"t2\"\n# binds = EXTI3\nprio = 2\n"));
+
+        // the skeleton loads as a task set, with the ceilings of the application
+        let tasks = parse_tasks(&toml, Format::Toml).unwrap();
+        assert_eq!(tasks[1].trace.to_string(), "[t2:0 [a:0 0] [b:0 0] 0]");
+        let (ip, tr) = pre_analysis(&tasks);
+        assert_eq!((ip["a"], ip["b"]), (2, 2));
+        assert_eq!(tr["t3"].len(), 1);
+        // idle holding b blocks t2 and t3
+        assert!(toml.contains("id = \"idle\"\n# idle\nprio = 0\n"));
+        assert_eq!(tasks[3].trace.to_string(), "[idle:0 [b:0 0] 0]");
+    }
+}
@@ -0,0 +1,253 @@
+use crate::common::*;
+use crate::srp_analysis::*;
+
+// Sensitivity analysis, how much the task set may change while remaining
+// schedulable. Each question is answered by a binary search over complete
+// analyses, as schedulability is monotonic in the parameters searched.
+
+/// Sensitivity of a single task
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskSensitivity {
+    pub id: String,
+    pub wcet: u64,
+    /// The largest WCET keeping the set schedulable, `None` if not schedulable
+    pub max_wcet: Option<u64>,
+    pub inter_arrival: u32,
+    /// The smallest inter-arrival time keeping the set schedulable
+    pub min_inter_arrival: Option<u32>,
+}
+
+impl TaskSensitivity {
+    /// Headroom in cycles, `max_wcet - wcet`
+    pub fn headroom(&self) -> Option<u64> {
+        self.max_wcet.map(|max| max - self.wcet)
+    }
+
+    /// Headroom in percent of the WCET
+    pub fn headroom_percent(&self) -> Option<f64> {
+        match self.headroom() {
+            Some(h) if self.wcet > 0 => Some(h as f64 * 100.0 / self.wcet as f64),
+            _ => None,
+        }
+    }
+}
+
+/// Sensitivity of the task set
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensitivity {
+    pub tasks: Vec<TaskSensitivity>,
+    /// The largest uniform scaling of all trace durations keeping the set
+    /// schedulable, in per mille, `None` if not schedulable even at zero
+    pub scaling: Option<u64>,
+}
+
+// the resolution of the scaling factor, 1/1000
+const PER_MILLE: u64 = 1000;
+
+/// Returns true if the task set is schedulable
+pub fn is_schedulable(tasks: &Tasks, config: &Config) -> bool {
+    let (ip, tr) = pre_analysis(tasks);
+    let analysis = srp_analysis(tasks, &ip, &tr, config);
+    return schedulability(tot_util_overheads(tasks, &config.overheads), &analysis).schedulable;
+}
+
+/// Returns the WCET sensitivity of each task and the critical scaling factor
+pub fn sensitivity(tasks: &Tasks, config: &Config) -> Sensitivity {
+    let tasks_sensitivity = tasks
+        .iter()
+        .enumerate()
+        .map(|(i, t)| TaskSensitivity {
+            id: t.id.clone(),
+            wcet: t.trace.end.wrapping_sub(t.trace.start) as u64,
+            max_wcet: max_wcet(tasks, i, config),
+            inter_arrival: t.inter_arrival,
+            min_inter_arrival: min_inter_arrival(tasks, i, config),
+        })
+        .collect();
+
+    Sensitivity {
+        tasks: tasks_sensitivity,
+        scaling: max_scaling(tasks, config),
+    }
+}
+
+/// Returns the largest WCET of task `i` that keeps the set schedulable. The
+/// task is prolonged after its last critical section, so its critical
+/// sections (and thereby the blocking it causes) are unchanged.
+pub fn max_wcet(tasks: &Tasks, i: usize, config: &Config) -> Option<u64> {
+    let t = &tasks[i];
+    let wcet = t.trace.end.wrapping_sub(t.trace.start) as u64;
+    let prolonged = |extra: u64| {
+        let mut tasks = tasks.clone();
+        tasks[i].trace.end = (t.trace.end as u64 + extra) as u32;
+        is_schedulable(&tasks, config)
+    };
+
+    // the WCET can never exceed the deadline, nor the u32 timestamps
+    let max_extra = (t.deadline as u64)
+        .saturating_sub(wcet)
+        .min(u32::MAX as u64 - t.trace.end as u64);
+
+    max_feasible(0, max_extra, prolonged).map(|extra| wcet + extra)
+}
+
+/// Returns the smallest inter-arrival time of task `i` that keeps the set
+/// schedulable. The analysis assumes constrained deadlines, `D(t)` <= `A(t)`,
+/// so a task with an implicit deadline (`D(t)` = `A(t)`) keeps it implicit,
+/// while the inter-arrival of other tasks is bounded below by `D(t)`.
+pub fn min_inter_arrival(tasks: &Tasks, i: usize, config: &Config) -> Option<u32> {
+    let t = &tasks[i];
+    let implicit = t.deadline == t.inter_arrival;
+    let arrival = |a: u64| {
+        let mut tasks = tasks.clone();
+        tasks[i].inter_arrival = a as u32;
+        if implicit {
+            tasks[i].deadline = a as u32;
+        }
+        is_schedulable(&tasks, config)
+    };
+
+    let lo = if implicit {
+        1
+    } else {
+        t.deadline.max(1) as u64
+    };
+    min_feasible(lo, u32::MAX as u64, arrival).map(|a| a as u32)
+}
+
+/// Returns the largest uniform scaling factor (in per mille) of all trace
+/// durations that keeps the set schedulable
+pub fn max_scaling(tasks: &Tasks, config: &Config) -> Option<u64> {
+    let scaled = |k: u64| match scale_tasks(tasks, k) {
+        Some(tasks) => is_schedulable(&tasks, config),
+        None => false,
+    };
+
+    // find an upper bound, unless the set is schedulable at any scale
+    let mut hi = PER_MILLE;
+    while scaled(hi) {
+        if hi >= u32::MAX as u64 {
+            return Some(hi);
+        }
+        hi *= 2;
+    }
+
+    max_feasible(0, hi, scaled)
+}
+
+// Scales the durations of all traces by `k` per mille
+fn scale_tasks(tasks: &Tasks, k: u64) -> Option<Tasks> {
+    tasks
+        .iter()
+        .map(|t| {
+            let trace = scale_trace(&t.trace, t.trace.start as u64, k)?;
+            Some(Task { trace, ..t.clone() })
+        })
+        .collect()
+}
+
+// Maps each timestamp `x` to `base + ceil((x - base) * k / 1000)`, which
+// is monotonic and thus preserves nesting and ordering of sections
+fn scale_trace(trace: &Trace, base: u64, k: u64) -> Option<Trace> {
+    let scale = |x: u32| {
+        let x = base + ((x as u64 - base) * k).div_ceil(PER_MILLE);
+        if x > u32::MAX as u64 {
+            None
+        } else {
+            Some(x as u32)
+        }
+    };
+
+    Some(Trace {
+        id: trace.id.clone(),
+        start: scale(trace.start)?,
+        end: scale(trace.end)?,
+        inner: trace
+            .inner
+            .iter()
+            .map(|i| scale_trace(i, base, k))
+            .collect::<Option<_>>()?,
+    })
+}
+
+// Returns the largest `x` in `lo..=hi` for which `feasible(x)` holds, given
+// that `feasible` is monotonically decreasing, `None` if not even `lo` is
+fn max_feasible(lo: u64, hi: u64, feasible: impl Fn(u64) -> bool) -> Option<u64> {
+    if !feasible(lo) {
+        return None;
+    }
+    let (mut lo, mut hi) = (lo, hi);
+    while lo < hi {
+        let mid = lo + (hi - lo).div_ceil(2);
+        if feasible(mid) {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    Some(lo)
+}
+
+// Returns the smallest `x` in `lo..=hi` for which `feasible(x)` holds, given
+// that `feasible` is monotonically increasing, `None` if not even `hi` is
+fn min_feasible(lo: u64, hi: u64, feasible: impl Fn(u64) -> bool) -> Option<u64> {
+    if !feasible(hi) {
+        return None;
+    }
+    let (mut lo, mut hi) = (lo, hi);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if feasible(mid) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    Some(lo)
+}
+
+#[cfg(test)]
+mod sensitivity_tests {
+    use super::*;
+    use crate::tasks::tasks;
+
+    #[test]
+    fn test_sensitivity() {
+        let tasks = tasks();
+        let s = sensitivity(&tasks, &Config::default());
+
+        // R(T1) = D(T1) = 100, any longer WCET makes T1 miss its deadline
+        let max: Vec<Option<u64>> = s.tasks.iter().map(|t| t.max_wcet).collect();
+        assert_eq!(max, vec![Some(10), Some(30), Some(30)]);
+        assert_eq!(s.tasks[0].headroom_percent(), Some(0.0));
+        assert_eq!(s.scaling, Some(1000));
+
+        // T2 may be released faster, as long as it preempts T1 only once
+        let min: Vec<Option<u32>> = s.tasks.iter().map(|t| t.min_inter_arrival).collect();
+        assert_eq!(min, vec![Some(100), Some(101), Some(50)]);
+    }
+
+    #[test]
+    fn test_scaling() {
+        let mut tasks = tasks();
+        // relax T1, so that its deadline is no longer binding
+        tasks[0].deadline = 1000;
+        tasks[0].inter_arrival = 1000;
+        let scaling = max_scaling(&tasks, &Config::default()).unwrap();
+        assert!(scaling > 1000);
+        assert!(is_schedulable(
+            &scale_tasks(&tasks, scaling).unwrap(),
+            &Config::default()
+        ));
+        assert!(!is_schedulable(
+            &scale_tasks(&tasks, scaling + 1).unwrap(),
+            &Config::default()
+        ));
+
+        let trace = scale_trace(&tasks[1].trace, 0, 1500).unwrap();
+        assert_eq!(
+            trace.to_string(),
+            "[T2:0 [R1:15 [R2:18 24] 30] [R1:33 42] 45]"
+        );
+    }
+}
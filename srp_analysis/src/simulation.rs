@@ -0,0 +1,590 @@
+use crate::common::*;
+use crate::srp_analysis::*;
+use std::collections::HashMap;
+use std::fmt;
+
+// Discrete-event simulation of a task set under the Stack Resource Policy.
+//
+// Each task `t` arrives at `offset(t) + k * A(t)`, k = 0, 1, ..., and each
+// job executes the trace of its task, locking and unlocking the resources of
+// the nested critical sections at their (relative) timestamps. A job may
+// start only if its priority is higher than that of the running job and
+// than the system ceiling, the highest ceiling `π(r)` of the resources
+// currently held. Once started, a job is never blocked, so started jobs form
+// a stack. Jobs of the same priority never preempt each other, whatever the
+// interference policy assumed by the analysis, pending jobs of the same
+// priority are started by vector table index, then by arrival.
+//
+// Release jitter is not simulated, each job is released on arrival.
+
+/// Simulation options
+#[derive(Debug, Clone, Default)]
+pub struct SimConfig {
+    /// The simulation ends at `horizon`, jobs arriving at or after it are
+    /// not released
+    pub horizon: u64,
+    /// Release offset of the first job of each task, 0 if not given
+    pub offsets: HashMap<String, u64>,
+    /// The simulation fails once the event log exceeds `max_events`,
+    /// unbounded if `None`
+    pub max_events: Option<usize>,
+}
+
+/// The event log bound of the command line
+pub const MAX_EVENTS: usize = 1_000_000;
+
+/// The simulation exceeded `SimConfig::max_events`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TooManyEvents {
+    pub max_events: usize,
+    /// The simulated time reached
+    pub time: u64,
+}
+
+impl fmt::Display for TooManyEvents {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the simulation exceeded {} events at time {}, give a shorter --horizon",
+            self.max_events, self.time
+        )
+    }
+}
+
+/// What happened to a job
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    Release,
+    Start,
+    Preempted,
+    Resume,
+    Lock(String),
+    Unlock(String),
+    Complete { response_time: u64 },
+    DeadlineMiss,
+}
+
+/// An entry of the job event log, `job` counts the jobs of `task` from 0
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub time: u64,
+    pub task: String,
+    pub job: u64,
+    pub kind: EventKind,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>8}: {}#{} ", self.time, self.task, self.job)?;
+        match &self.kind {
+            EventKind::Release => write!(f, "released"),
+            EventKind::Start => write!(f, "started"),
+            EventKind::Preempted => write!(f, "preempted"),
+            EventKind::Resume => write!(f, "resumed"),
+            EventKind::Lock(r) => write!(f, "locks {}", r),
+            EventKind::Unlock(r) => write!(f, "unlocks {}", r),
+            EventKind::Complete { response_time } => {
+                write!(f, "completed, response time {}", response_time)
+            }
+            EventKind::DeadlineMiss => write!(f, "missed its deadline"),
+        }
+    }
+}
+
+/// A time interval `start..end` in which a job executes holding the
+/// resources `held` (outermost first)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slice {
+    pub task: String,
+    pub job: u64,
+    pub start: u64,
+    pub end: u64,
+    pub held: Vec<String>,
+}
+
+/// Observed behaviour of the jobs of a task
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskSimulation {
+    pub id: String,
+    pub released: u64,
+    pub completed: u64,
+    /// The largest response time among the completed jobs
+    pub max_rt: Option<u64>,
+    pub misses: u64,
+}
+
+/// Result of a simulation
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub horizon: u64,
+    pub events: Vec<Event>,
+    pub slices: Vec<Slice>,
+    pub tasks: Vec<TaskSimulation>,
+}
+
+// A lock or unlock at `offset` cycles into the job
+#[derive(Debug, Clone)]
+enum Action {
+    Lock(String),
+    Unlock(String),
+}
+
+#[derive(Debug)]
+struct Job {
+    task: usize,
+    job: u64,
+    arrival: u64,
+    deadline: u64,
+    length: u64,
+    executed: u64,
+    // position in the actions of the task
+    cursor: usize,
+    held: Vec<String>,
+    started: bool,
+    missed: bool,
+}
+
+/// Returns the least common multiple of all inter-arrival times, `None` on
+/// overflow
+pub fn hyperperiod(tasks: &Tasks) -> Option<u64> {
+    tasks.iter().try_fold(1u64, |l, t| {
+        let a = t.inter_arrival as u64;
+        (l / gcd(l, a)).checked_mul(a)
+    })
+}
+
+/// Returns the length of the synchronous busy period, from the release of
+/// all tasks at time 0 until the processor first idles, the first job of each
+/// task completing within it. `None` unless the load is below 1, or on overflow.
+pub fn busy_period(tasks: &Tasks) -> Option<u64> {
+    if tot_util(tasks) >= Ratio::from(1) {
+        return None;
+    }
+    let mut length: u64 = tasks.iter().map(wcet).sum();
+    loop {
+        let next = tasks.iter().try_fold(0u64, |sum, t| {
+            length
+                .div_ceil(t.inter_arrival as u64)
+                .checked_mul(wcet(t))
+                .and_then(|w| sum.checked_add(w))
+        })?;
+        if next == length {
+            return Some(length);
+        }
+        length = next;
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Flattens the critical sections of a trace into actions, in order of time.
+// Siblings need not be given in order of time, see `validate`.
+fn actions(trace: &Trace, base: u32, out: &mut Vec<(u64, Action)>) {
+    let mut inner: Vec<&Trace> = trace.inner.iter().collect();
+    inner.sort_by_key(|cs| (cs.start, cs.end));
+    for cs in inner {
+        out.push((
+            cs.start.saturating_sub(base) as u64,
+            Action::Lock(cs.id.clone()),
+        ));
+        actions(cs, base, out);
+        out.push((
+            cs.end.saturating_sub(base) as u64,
+            Action::Unlock(cs.id.clone()),
+        ));
+    }
+}
+
+/// Simulates the task set from time 0 to `config.horizon`. The resource
+/// ceilings are taken from `ip`, see `pre_analysis`.
+pub fn simulate(
+    tasks: &Tasks,
+    ip: &IdPrio,
+    config: &SimConfig,
+) -> Result<Simulation, TooManyEvents> {
+    let task_actions: Vec<Vec<(u64, Action)>> = tasks
+        .iter()
+        .map(|t| {
+            let mut out = vec![];
+            actions(&t.trace, t.trace.start, &mut out);
+            out
+        })
+        .collect();
+    let offset = |t: &Task| *config.offsets.get(&t.id).unwrap_or(&0);
+
+    let mut sim = Simulation {
+        horizon: config.horizon,
+        events: vec![],
+        slices: vec![],
+        tasks: tasks
+            .iter()
+            .map(|t| TaskSimulation {
+                id: t.id.clone(),
+                released: 0,
+                completed: 0,
+                max_rt: None,
+                misses: 0,
+            })
+            .collect(),
+    };
+    let mut next_arrival: Vec<u64> = tasks.iter().map(offset).collect();
+    let mut pending: Vec<Job> = vec![];
+    // indices into `pending` of the started jobs, the running job on top
+    let mut stack: Vec<usize> = vec![];
+    let mut running: Option<usize> = None;
+    let mut time = 0;
+
+    let event = |sim: &mut Simulation, time: u64, job: &Job, kind: EventKind| {
+        sim.events.push(Event {
+            time,
+            task: tasks[job.task].id.clone(),
+            job: job.job,
+            kind,
+        })
+    };
+
+    loop {
+        if let Some(max_events) = config.max_events {
+            if sim.events.len() > max_events {
+                return Err(TooManyEvents { max_events, time });
+            }
+        }
+
+        // lock and unlock resources at the current position of the running job
+        if let Some(&top) = stack.last() {
+            let job = &mut pending[top];
+            while let Some((at, action)) = task_actions[job.task].get(job.cursor) {
+                if *at > job.executed {
+                    break;
+                }
+                let kind = match action {
+                    Action::Lock(r) => {
+                        job.held.push(r.clone());
+                        EventKind::Lock(r.clone())
+                    }
+                    Action::Unlock(r) => {
+                        job.held.retain(|h| h != r);
+                        EventKind::Unlock(r.clone())
+                    }
+                };
+                job.cursor += 1;
+                event(&mut sim, time, job, kind);
+            }
+
+            if job.executed >= job.length {
+                let response_time = time - job.arrival;
+                event(&mut sim, time, job, EventKind::Complete { response_time });
+                let ts = &mut sim.tasks[job.task];
+                ts.completed += 1;
+                ts.max_rt = ts.max_rt.max(Some(response_time));
+                if time > job.deadline && !job.missed {
+                    ts.misses += 1;
+                    event(&mut sim, time, job, EventKind::DeadlineMiss);
+                }
+                stack.pop();
+                pending.remove(top);
+                for j in stack.iter_mut() {
+                    if *j > top {
+                        *j -= 1;
+                    }
+                }
+                running = None;
+                continue;
+            }
+        }
+
+        // jobs still pending at their deadline
+        for job in pending.iter_mut() {
+            if !job.missed && job.deadline <= time {
+                job.missed = true;
+                sim.tasks[job.task].misses += 1;
+                event(&mut sim, job.deadline, job, EventKind::DeadlineMiss);
+            }
+        }
+
+        if time >= config.horizon {
+            break;
+        }
+
+        // release arrivals
+        for (i, t) in tasks.iter().enumerate() {
+            while next_arrival[i] <= time {
+                let job = Job {
+                    task: i,
+                    job: sim.tasks[i].released,
+                    arrival: next_arrival[i],
+                    deadline: next_arrival[i] + t.deadline as u64,
+                    length: t.trace.end.saturating_sub(t.trace.start) as u64,
+                    executed: 0,
+                    cursor: 0,
+                    held: vec![],
+                    started: false,
+                    missed: false,
+                };
+                event(&mut sim, next_arrival[i], &job, EventKind::Release);
+                sim.tasks[i].released += 1;
+                pending.push(job);
+                next_arrival[i] += t.inter_arrival.max(1) as u64;
+            }
+        }
+
+        // the highest priority job not yet started, earliest arrival first
+        let candidate = (0..pending.len())
+            .filter(|j| !pending[*j].started)
+            .min_by_key(|j| {
+                let t = &tasks[pending[*j].task];
+                (
+                    std::cmp::Reverse(t.prio),
+                    t.vector.unwrap_or(u32::MAX),
+                    pending[*j].arrival,
+                    pending[*j].task,
+                )
+            });
+        let ceiling = stack
+            .iter()
+            .flat_map(|j| pending[*j].held.iter())
+            .map(|r| ip[r])
+            .max();
+        if let Some(c) = candidate {
+            let task = &tasks[pending[c].task];
+            let starts = match stack.last() {
+                None => true,
+                Some(top) => {
+                    task.prio > tasks[pending[*top].task].prio
+                        && ceiling.is_none_or(|ceiling| task.prio > ceiling)
+                }
+            };
+            if starts {
+                stack.push(c);
+            }
+        }
+
+        // dispatch the top of the stack
+        let top = stack.last().copied();
+        if top != running {
+            if let Some(prev) = running {
+                event(&mut sim, time, &pending[prev], EventKind::Preempted);
+            }
+            if let Some(top) = top {
+                let job = &mut pending[top];
+                let kind = if job.started {
+                    EventKind::Resume
+                } else {
+                    EventKind::Start
+                };
+                job.started = true;
+                event(&mut sim, time, job, kind);
+            }
+            running = top;
+        }
+
+        // advance to the next event
+        let mut next = config.horizon;
+        next = next.min(*next_arrival.iter().min().unwrap_or(&u64::MAX));
+        for job in &pending {
+            if !job.missed {
+                next = next.min(job.deadline);
+            }
+        }
+        if let Some(top) = top {
+            let job = &mut pending[top];
+            let until = match task_actions[job.task].get(job.cursor) {
+                Some((at, _)) => *at,
+                None => job.length,
+            };
+            next = next.min(time + until - job.executed);
+            job.executed += next - time;
+
+            let (task, held) = (tasks[job.task].id.clone(), job.held.clone());
+            match sim.slices.last_mut() {
+                _ if next == time => {}
+                Some(s)
+                    if s.end == time && s.task == task && s.job == job.job && s.held == held =>
+                {
+                    s.end = next
+                }
+                _ => sim.slices.push(Slice {
+                    task,
+                    job: job.job,
+                    start: time,
+                    end: next,
+                    held,
+                }),
+            }
+        }
+        time = next;
+    }
+
+    Ok(sim)
+}
+
+#[cfg(test)]
+mod simulation_tests {
+    use super::*;
+    use crate::tasks::tasks;
+
+    #[test]
+    fn test_synchronous() {
+        let tasks = tasks();
+        let (ip, tr) = pre_analysis(&tasks);
+        let config = SimConfig {
+            horizon: hyperperiod(&tasks).unwrap(),
+            ..SimConfig::default()
+        };
+        assert_eq!(config.horizon, 200);
+        let sim = simulate(&tasks, &ip, &config).unwrap();
+
+        let analysis = srp_analysis(&tasks, &ip, &tr, &Config::default());
+        for (s, a) in sim.tasks.iter().zip(&analysis) {
+            assert_eq!(s.released, s.completed);
+            assert_eq!(s.misses, 0);
+            assert!(s.max_rt.unwrap() <= a.rt.unwrap());
+        }
+        let max_rt: Vec<Option<u64>> = sim.tasks.iter().map(|s| s.max_rt).collect();
+        // T3 preempts T2 at 50, between its critical sections
+        assert_eq!(max_rt, vec![Some(100), Some(90), Some(30)]);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let tasks = tasks();
+        // T1 completes last, at 100, see above
+        assert_eq!(busy_period(&tasks), Some(100));
+        let (ip, _) = pre_analysis(&tasks);
+        let config = SimConfig {
+            horizon: 200,
+            max_events: Some(10),
+            ..SimConfig::default()
+        };
+        assert_eq!(
+            simulate(&tasks, &ip, &config).unwrap_err().to_string(),
+            "the simulation exceeded 10 events at time 50, give a shorter --horizon"
+        );
+
+        // overloaded, the busy period does not end
+        let tasks = vec![test_task("T", 1, 10, "[T:0 20]")];
+        assert_eq!(busy_period(&tasks), None);
+    }
+
+    #[test]
+    fn test_blocking() {
+        // L locks R at 1, H (using R) arrives at 2 and is blocked until 5
+        let tasks = vec![
+            test_task("L", 1, 100, "[L:0 [R:1 5] 6]"),
+            test_task("H", 2, 100, "[H:0 [R:1 2] 3]"),
+        ];
+        let (ip, _) = pre_analysis(&tasks);
+        let mut config = SimConfig {
+            horizon: 100,
+            ..SimConfig::default()
+        };
+        config.offsets.insert("H".to_string(), 2);
+        let sim = simulate(&tasks, &ip, &config).unwrap();
+
+        let log: Vec<String> = sim.events.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            log,
+            vec![
+                "       0: L#0 released",
+                "       0: L#0 started",
+                "       1: L#0 locks R",
+                "       2: H#0 released",
+                "       5: L#0 unlocks R",
+                "       5: L#0 preempted",
+                "       5: H#0 started",
+                "       6: H#0 locks R",
+                "       7: H#0 unlocks R",
+                "       8: H#0 completed, response time 6",
+                "       8: L#0 resumed",
+                "       9: L#0 completed, response time 9",
+            ]
+        );
+        assert_eq!(
+            sim.slices[1],
+            Slice {
+                task: "L".to_string(),
+                job: 0,
+                start: 1,
+                end: 5,
+                held: vec!["R".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_same_priority() {
+        // B has the lower vector index, but does not preempt A of equal priority
+        let mut tasks = vec![
+            test_task("A", 1, 100, "[A:0 10]"),
+            test_task("B", 1, 100, "[B:0 10]"),
+            test_task("C", 1, 100, "[C:0 10]"),
+        ];
+        tasks[0].vector = Some(2);
+        tasks[1].vector = Some(0);
+        tasks[2].vector = Some(1);
+        let (ip, _) = pre_analysis(&tasks);
+        let mut config = SimConfig {
+            horizon: 100,
+            ..SimConfig::default()
+        };
+        config.offsets.insert("B".to_string(), 5);
+        config.offsets.insert("C".to_string(), 1);
+        let sim = simulate(&tasks, &ip, &config).unwrap();
+        assert!(!sim.events.iter().any(|e| e.kind == EventKind::Preempted));
+        // pending at 10, B is served before C by vector index
+        let max_rt: Vec<Option<u64>> = sim.tasks.iter().map(|s| s.max_rt).collect();
+        assert_eq!(max_rt, vec![Some(10), Some(15), Some(29)]);
+    }
+
+    #[test]
+    fn test_unordered_sections() {
+        let tasks = vec![test_task("T", 1, 100, "[T:0 [R2:20 25] [R1:5 10] 30]")];
+        let (ip, _) = pre_analysis(&tasks);
+        let config = SimConfig {
+            horizon: 100,
+            ..SimConfig::default()
+        };
+        let sim = simulate(&tasks, &ip, &config).unwrap();
+        let locks: Vec<String> = sim
+            .events
+            .iter()
+            .filter(|e| matches!(e.kind, EventKind::Lock(_) | EventKind::Unlock(_)))
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            locks,
+            vec![
+                "       5: T#0 locks R1",
+                "      10: T#0 unlocks R1",
+                "      20: T#0 locks R2",
+                "      25: T#0 unlocks R2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deadline_miss() {
+        let tasks = vec![
+            test_task("T1", 1, 30, "[T1:0 20]"),
+            test_task("T2", 2, 30, "[T2:0 20]"),
+        ];
+        let (ip, _) = pre_analysis(&tasks);
+        let config = SimConfig {
+            horizon: 30,
+            ..SimConfig::default()
+        };
+        let sim = simulate(&tasks, &ip, &config).unwrap();
+        assert_eq!(sim.tasks[0].misses, 1);
+        assert_eq!(sim.tasks[0].completed, 0);
+        assert_eq!(
+            sim.events.last().unwrap().to_string(),
+            "      30: T1#0 missed its deadline"
+        );
+    }
+}
@@ -2,177 +2,599 @@ use crate::common::*;
 use crate::tasks::*;
 use std::collections::{HashMap, HashSet};
 
+/// Analysis options
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Over-approximate the busy-period by the deadline, `Bp(t)` = `D(t)`
+    pub approx: bool,
+    /// Record the iterations of the exact response time recurrence
+    pub record: bool,
+    /// Which tasks are considered to preempt a task
+    pub policy: Policy,
+    /// Scheduling overheads, in cycles
+    pub overheads: Overheads,
+}
+
+/// Scheduling overheads, in cycles, all zero by default
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Overheads {
+    /// Context switch cost of each preemption, added to the interference
+    pub preemption: u32,
+    /// Cost of entering a critical section (raising the system ceiling)
+    pub lock: u32,
+    /// Cost of leaving a critical section (restoring the system ceiling)
+    pub unlock: u32,
+    /// Interrupt entry and exit of each task, added to its WCET
+    pub dispatch: u32,
+}
+
+impl Overheads {
+    pub fn is_zero(&self) -> bool {
+        *self == Overheads::default()
+    }
+}
+
+/// Interference policy for tasks of the same priority
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Policy {
+    /// Only higher priority tasks preempt, `P(h)` > `P(t)`
+    #[default]
+    Strict,
+    /// Tasks of same or higher priority preempt, `P(h)` >= `P(t)`
+    SameOrHigher,
+    /// Tasks of the same priority are ordered by their vector table index,
+    /// a lower index is served first. Tasks without a `vector` are treated
+    /// as under `SameOrHigher`.
+    VectorOrder,
+}
+
+impl std::str::FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(Policy::Strict),
+            "same-or-higher" => Ok(Policy::SameOrHigher),
+            "vector" => Ok(Policy::VectorOrder),
+            _ => Err(format!(
+                "unknown policy `{}`, expected `strict`, `same-or-higher` or `vector`",
+                s
+            )),
+        }
+    }
+}
+
+/// Returns true if task `h` may preempt (interfere with) task `t`
+pub fn preempts(h: &Task, t: &Task, policy: Policy) -> bool {
+    if h.id == t.id {
+        return false;
+    }
+
+    return match policy {
+        Policy::Strict => h.prio > t.prio,
+        Policy::SameOrHigher => h.prio >= t.prio,
+        Policy::VectorOrder => {
+            h.prio > t.prio
+                || h.prio == t.prio
+                    && match (h.vector, t.vector) {
+                        (Some(h_vector), Some(t_vector)) => h_vector < t_vector,
+                        _ => true,
+                    }
+        }
+    };
+}
+
+/// Verdict of a task whose busy-period exceeded its deadline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeadlineMiss {
+    /// The busy-period value at which `D(t)` was exceeded (with `J(t)` added)
+    pub busy_period: u64,
+}
+
+/// Analysis result of a single task, `rt` and `pre` carry the deadline
+/// miss in case the response time recurrence did not converge within `D(t)`
+#[derive(Debug, Clone)]
+pub struct TaskAnalysis {
+    pub id: String,
+    pub deadline: u32,
+    pub jitter: u32,
+    pub rt: Result<u64, DeadlineMiss>,
+    /// The part of `rt` due to scheduling overheads
+    pub overhead: Option<u64>,
+    /// The WCET, including overheads
+    pub wcet: u64,
+    pub bt: u64,
+    /// The section causing `bt`, `None` if the task is never blocked
+    pub blocker: Option<Blocker>,
+    pub pre: Result<u64, DeadlineMiss>,
+    /// The interference of each preempting task making up `pre`, empty on a
+    /// deadline miss
+    pub interference: Vec<Term>,
+    /// `R_0`, `R_1`, ... of the exact recurrence, if recorded
+    pub iterations: Vec<Iteration>,
+}
+
+/// One iteration `R_s` = `C(t)` + `B(t)` + sum(`ceil((R_(s-1) + J(h))/A(h))` * `C(h)`)
+/// of the response time recurrence, the response time being `J(t)` + `R_s`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Iteration {
+    pub value: u64,
+    /// The interference terms, empty for the base case `R_0`
+    pub terms: Vec<Term>,
+}
+
+/// The interference `ceil((prev + J(h))/A(h))` * `C(h)` of a preempting task `h`,
+/// where `prev` is the previous busy-period (or `D(t)` when approximated), and
+/// `wcet` includes the overheads of `h` and of the preemption
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub task: String,
+    pub prev: u64,
+    pub jitter: u32,
+    pub inter_arrival: u32,
+    pub wcet: u64,
+}
+
+impl Term {
+    pub fn preemptions(&self) -> u64 {
+        (self.prev + self.jitter as u64).div_ceil(self.inter_arrival as u64)
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.preemptions() * self.wcet
+    }
+}
+
 /// Returns the total load factor of the CPU
-pub fn tot_util(tasks: &Vec<Task>) -> f32 {
-    let mut total_load_factor: f32 = 0.0;
+pub fn tot_util(tasks: &Vec<Task>) -> Ratio {
+    return tot_util_overheads(tasks, &Overheads::default());
+}
+
+/// Returns the total load factor, with the cost of each task including overheads
+pub fn tot_util_overheads(tasks: &Vec<Task>, overheads: &Overheads) -> Ratio {
+    let mut total_load_factor = Ratio::from(0);
 
     for t in tasks {
-        total_load_factor += load_factor(&t);
+        total_load_factor = total_load_factor + load_factor(t, overheads);
     }
 
     return total_load_factor;
 }
 
 /// Returns the load factor of a single task
-fn load_factor(task: &Task) -> f32 {
-    return wcet(&task) / task.inter_arrival as f32;
+fn load_factor(task: &Task, overheads: &Overheads) -> Ratio {
+    return Ratio::new(cost(task, overheads), task.inter_arrival as u64);
 }
 
 /// Returns worst case execution time of a task
-fn wcet(task: &Task) -> f32 {
-    return task.trace.end.wrapping_sub(task.trace.start) as f32;
+pub fn wcet(task: &Task) -> u64 {
+    return task.trace.end.wrapping_sub(task.trace.start) as u64;
+}
+
+/// Returns the cost of a task including overheads, its WCET plus the dispatch
+/// and the entry and exit of each critical section
+pub fn cost(task: &Task, overheads: &Overheads) -> u64 {
+    return wcet(task)
+        + overheads.dispatch as u64
+        + (overheads.lock as u64 + overheads.unlock as u64) * sections(&task.trace);
 }
 
-/// Returns the response time of a task
-fn response_time(
+/// Returns the number of critical sections of a trace
+fn sections(trace: &Trace) -> u64 {
+    return trace.inner.iter().map(|i| 1 + sections(i)).sum();
+}
+
+/// Returns the response time of a task, `R(t)` = `J(t)` + `B(t)` + `C(t)` + `I(t)`
+pub fn response_time(
     task: &Task,
     tasks: &Vec<Task>,
     ip: &HashMap<String, u8>,
     tr: &HashMap<String, HashSet<String>>,
-    approx: bool,
-) -> f32 {
-    return blocking_time(&task, tasks, ip, tr)
-        + wcet(&task)
-        + preemption(&task, tasks, ip, tr, approx);
+    config: &Config,
+) -> Result<u64, DeadlineMiss> {
+    let (preemption, _) = preemption(task, tasks, ip, tr, config)?;
+    return Ok(task.jitter as u64
+        + blocking_time(task, tasks, ip, tr, config)
+        + cost(task, &config.overheads)
+        + preemption);
 }
 
-/// Returns the blocking time of a task
+/// Returns the blocking time of a task, including the entry and exit of the
+/// blocking critical section (or the cost of a blocking task)
 fn blocking_time(
     task: &Task,
     tasks: &Vec<Task>,
     ip: &HashMap<String, u8>,
     tr: &HashMap<String, HashSet<String>>,
-) -> f32 {
-    let mut blocking_time: f32 = 0.0;
-    let mut resources = &HashSet::new();
-
-    // Retrieve resources used by the task
-    match tr.get(&task.id) {
-        Some(r) => resources = r,
-        None => (),
+    config: &Config,
+) -> u64 {
+    let o = &config.overheads;
+    return match blocker_overheads(task, tasks, ip, tr, config.policy, o) {
+        Some(b) => blocking_cost(&b, tasks, o),
+        None => 0,
+    };
+}
+
+/// Returns the cost of a blocking section, its length plus the entry and exit
+/// of the critical section, or the cost of a blocking task
+fn blocking_cost(b: &Blocker, tasks: &Vec<Task>, overheads: &Overheads) -> u64 {
+    return match b.resource {
+        Some(_) => b.length() + overheads.lock as u64 + overheads.unlock as u64,
+        None => cost(tasks.iter().find(|t| t.id == b.task).unwrap(), overheads),
+    };
+}
+
+/// The section of a lower priority task that causes the blocking time `B(t)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blocker {
+    pub task: String,
+    /// The resource held, `None` if the whole task blocks (under `VectorOrder`)
+    pub resource: Option<String>,
+    /// The interval of the section in the trace of `task`
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Blocker {
+    pub fn length(&self) -> u64 {
+        self.end.wrapping_sub(self.start) as u64
+    }
+}
+
+impl std::fmt::Display for Blocker {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.resource {
+            Some(r) => write!(
+                f,
+                "{} holding {} at {}..{}",
+                self.task, r, self.start, self.end
+            ),
+            None => write!(f, "{} at {}..{}", self.task, self.start, self.end),
+        }
     }
+}
+
+/// Returns the longest section blocking a task, if any. Ties are resolved to
+/// the first task in the task set, and the lexicographically smallest resource.
+pub fn blocker(
+    task: &Task,
+    tasks: &Vec<Task>,
+    ip: &HashMap<String, u8>,
+    tr: &HashMap<String, HashSet<String>>,
+    policy: Policy,
+) -> Option<Blocker> {
+    return blocker_overheads(task, tasks, ip, tr, policy, &Overheads::default());
+}
+
+/// Returns the section blocking a task at the highest cost including
+/// overheads, see `blocking_cost`, if any. Ties are resolved as by `blocker`.
+pub fn blocker_overheads(
+    task: &Task,
+    tasks: &Vec<Task>,
+    ip: &HashMap<String, u8>,
+    tr: &HashMap<String, HashSet<String>>,
+    policy: Policy,
+    overheads: &Overheads,
+) -> Option<Blocker> {
+    let mut blocker: Option<(u64, Blocker)> = None;
+    let mut update = |b: Blocker| {
+        let cost = blocking_cost(&b, tasks, overheads);
+        if blocker.as_ref().is_none_or(|(old, _)| cost > *old) {
+            blocker = Some((cost, b));
+        }
+    };
 
     // if the prio of t is lower than the task prio and t holds a resource with a
     // resource prio >= task prio. then get max critical section of the resource.
-    for r in resources {
-        for t in tasks {
-            if let Some(r_prio) = ip.get(r) {
-                if t.prio < task.prio && r_prio >= &task.prio {
-                    let wcet_resource = wcet_resource(&t.trace, r);
-                    if wcet_resource > blocking_time {
-                        blocking_time = wcet_resource;
+    // The resource need not be used by the task itself, its ceiling alone
+    // prevents the task from starting.
+    for t in tasks {
+        if t.prio >= task.prio {
+            continue;
+        }
+        if let Some(resources) = tr.get(&t.id) {
+            let mut resources: Vec<&String> = resources.iter().collect();
+            resources.sort();
+            for r in resources {
+                if let Some(r_prio) = ip.get(r) {
+                    if r_prio >= &task.prio {
+                        if let Some(cs) = longest_section(&t.trace, r) {
+                            update(Blocker {
+                                task: t.id.clone(),
+                                resource: Some(r.clone()),
+                                start: cs.start,
+                                end: cs.end,
+                            });
+                        }
                     }
                 }
             }
         }
     }
 
-    return blocking_time;
+    // under vector table order, a task of the same priority that is served
+    // after us cannot be preempted once started, it blocks us for its WCET
+    if policy == Policy::VectorOrder {
+        for t in tasks {
+            if t.id != task.id && t.prio == task.prio && !preempts(t, task, policy) {
+                update(Blocker {
+                    task: t.id.clone(),
+                    resource: None,
+                    start: t.trace.start,
+                    end: t.trace.end,
+                });
+            }
+        }
+    }
+
+    return blocker.map(|(_, b)| b);
 }
 
-/// Returns the worst case execution time of a trace
-fn wcet_resource(trace: &Trace, resource: &str) -> f32 {
-    let mut wcet: f32 = 0.0;
+/// Returns the longest critical section of `resource` in a trace
+pub fn wcet_resource(trace: &Trace, resource: &str) -> u64 {
+    return match longest_section(trace, resource) {
+        Some(cs) => cs.end.wrapping_sub(cs.start) as u64,
+        None => 0,
+    };
+}
 
+/// Returns the longest (first if several) critical section of `resource`
+pub fn longest_section<'a>(trace: &'a Trace, resource: &str) -> Option<&'a Trace> {
     if trace.id == resource {
-        wcet = trace.end as f32 - trace.start as f32;
-    } else if trace.inner.len() != 0 {
-        for i in &trace.inner {
-            let temp_wcet = wcet_resource(&i, resource);
-            if temp_wcet > wcet {
-                wcet = temp_wcet;
+        return Some(trace);
+    }
+
+    let mut longest: Option<&Trace> = None;
+    for i in &trace.inner {
+        if let Some(cs) = longest_section(i, resource) {
+            let length = |t: &Trace| t.end.wrapping_sub(t.start);
+            if longest.is_none_or(|l| length(cs) > length(l)) {
+                longest = Some(cs);
             }
         }
     }
 
-    return wcet;
+    return longest;
 }
 
-/// Returns either the approx preemption time or the exact preemption time of a task
+/// Returns either the approx preemption time or the exact preemption time of a task,
+/// with the interference of each preempting task
 fn preemption(
     task: &Task,
     tasks: &Vec<Task>,
     ip: &HashMap<String, u8>,
     tr: &HashMap<String, HashSet<String>>,
-    approx: bool,
-) -> f32 {
-    let preemption;
-
-    if approx {
-        preemption = preemption_approx(task, tasks);
+    config: &Config,
+) -> Result<(u64, Vec<Term>), DeadlineMiss> {
+    let preemption = if config.approx {
+        let terms = preemption_approx(task, tasks, config);
+        Ok((terms.iter().map(|t| t.cycles()).sum(), terms))
     } else {
-        let busy_period = wcet(task) + blocking_time(task, tasks, ip, tr);
-        preemption = preemption_exact(task, tasks, ip, tr, busy_period, busy_period);
-    }
+        let busy_period =
+            cost(task, &config.overheads) + blocking_time(task, tasks, ip, tr, config);
+        preemption_exact(task, tasks, config, busy_period, None)
+    };
 
     return preemption;
 }
 
-/// Returns approx preemption time
-fn preemption_approx(task: &Task, tasks: &Vec<Task>) -> f32 {
-    let mut preemption = 0.0;
+/// Returns the iterations `R_0`, `R_1`, ... of the exact response time recurrence
+pub fn recurrence(
+    task: &Task,
+    tasks: &Vec<Task>,
+    ip: &HashMap<String, u8>,
+    tr: &HashMap<String, HashSet<String>>,
+    config: &Config,
+) -> Vec<Iteration> {
+    let busy_period = cost(task, &config.overheads) + blocking_time(task, tasks, ip, tr, config);
+    let mut iterations = vec![Iteration {
+        value: busy_period,
+        terms: vec![],
+    }];
+    let _ = preemption_exact(task, tasks, config, busy_period, Some(&mut iterations));
+
+    return iterations;
+}
+
+/// Returns the approx interference terms, the busy-period taken as `D(t)`
+fn preemption_approx(task: &Task, tasks: &Vec<Task>, config: &Config) -> Vec<Term> {
+    let mut terms = vec![];
 
     for t in tasks {
-        if t.prio > task.prio {
-            preemption += wcet(t) * (task.deadline as f32 / t.inter_arrival as f32).ceil();
+        if preempts(t, task, config.policy) {
+            terms.push(Term {
+                task: t.id.clone(),
+                prev: task.deadline as u64,
+                jitter: t.jitter,
+                inter_arrival: t.inter_arrival,
+                wcet: preemption_cost(t, &config.overheads),
+            });
         }
     }
 
-    return preemption;
+    return terms;
 }
 
 /// Returns exact preemption time, based on the response time recurrence eq.
-/// 7.22 in Hard Real-Time Computing Systems.
+/// 7.22 in Hard Real-Time Computing Systems, with the terms of the converged
+/// iteration.
+/// The recurrence is terminated with a `DeadlineMiss` once `J(t)` plus the
+/// busy-period exceeds `D(t)`.
+/// Each iteration is appended to `iterations`, if given.
 fn preemption_exact(
     task: &Task,
     tasks: &Vec<Task>,
-    ip: &HashMap<String, u8>,
-    tr: &HashMap<String, HashSet<String>>,
-    busy_period: f32,
-    prev: f32,
-) -> f32 {
-    let mut curr = 0.0;
+    config: &Config,
+    busy_period: u64,
+    mut iterations: Option<&mut Vec<Iteration>>,
+) -> Result<(u64, Vec<Term>), DeadlineMiss> {
+    let mut prev = busy_period;
+    loop {
+        if task.jitter as u64 + prev > task.deadline as u64 {
+            return Err(DeadlineMiss { busy_period: prev });
+        }
 
-    if busy_period > task.deadline as f32 {
-        panic!("task non-schedulable: deadline miss!")
-    } else {
-        curr += busy_period;
+        let mut terms = vec![];
         for t in tasks {
-            if t.prio > task.prio {
-                let preemption = (prev / t.inter_arrival as f32).ceil() * wcet(t);
-                curr += preemption;
+            if preempts(t, task, config.policy) {
+                terms.push(Term {
+                    task: t.id.clone(),
+                    prev,
+                    jitter: t.jitter,
+                    inter_arrival: t.inter_arrival,
+                    wcet: preemption_cost(t, &config.overheads),
+                });
             }
         }
+        let curr = busy_period + terms.iter().map(|t| t.cycles()).sum::<u64>();
+        if let Some(iterations) = &mut iterations {
+            iterations.push(Iteration {
+                value: curr,
+                terms: terms.clone(),
+            });
+        }
+
         if curr == prev {
-            return curr - busy_period;
-        } else {
-            preemption_exact(task, tasks, ip, tr, busy_period, curr)
+            return Ok((curr - busy_period, terms));
         }
+        prev = curr;
     }
 }
 
-/// Returns a compiled analysis of the system
+/// Returns the cost of a preempting task, including the context switch
+fn preemption_cost(task: &Task, overheads: &Overheads) -> u64 {
+    return cost(task, overheads) + overheads.preemption as u64;
+}
+
+/// Returns a compiled analysis of the system, the task set is assumed to
+/// pass `validate`
 pub fn srp_analysis(
     tasks: &Vec<Task>,
     ip: &HashMap<String, u8>,
     tr: &HashMap<String, HashSet<String>>,
-    approx: bool,
-) -> Vec<(String, f32, f32, f32, f32)> {
+    config: &Config,
+) -> Vec<TaskAnalysis> {
     let mut v = Vec::new();
 
     for t in tasks {
-        v.push((
-            t.id.to_string(),
-            response_time(t, tasks, ip, tr, approx),
-            wcet(t),
-            blocking_time(t, tasks, ip, tr),
-            preemption(t, tasks, ip, tr, approx),
-        ))
+        let iterations = if config.record && !config.approx {
+            recurrence(t, tasks, ip, tr, config)
+        } else {
+            vec![]
+        };
+
+        let pre = preemption(t, tasks, ip, tr, config);
+        let rt = response_time(t, tasks, ip, tr, config);
+        // the part of the response time due to overheads
+        let overhead = if config.overheads.is_zero() {
+            rt.ok().map(|_| 0)
+        } else {
+            let config = Config {
+                overheads: Overheads::default(),
+                ..config.clone()
+            };
+            match (rt, response_time(t, tasks, ip, tr, &config)) {
+                (Ok(rt), Ok(without)) => Some(rt - without),
+                _ => None,
+            }
+        };
+        v.push(TaskAnalysis {
+            id: t.id.to_string(),
+            deadline: t.deadline,
+            jitter: t.jitter,
+            rt,
+            overhead,
+            wcet: cost(t, &config.overheads),
+            bt: blocking_time(t, tasks, ip, tr, config),
+            blocker: blocker_overheads(t, tasks, ip, tr, config.policy, &config.overheads),
+            pre: pre.as_ref().map(|(pre, _)| *pre).map_err(|miss| *miss),
+            interference: pre.map(|(_, terms)| terms).unwrap_or_default(),
+            iterations,
+        })
     }
 
     return v;
 }
 
+/// A schedulability condition that does not hold
+#[derive(Debug, Clone, PartialEq)]
+pub enum Failure {
+    /// `Ltot` >= 1
+    Overload { tot_util: Ratio },
+    /// `R(t)` > `D(t)`, or `J(t)` plus the recurrence exceeded `D(t)`
+    DeadlineMiss {
+        task: String,
+        rt: u64,
+        deadline: u32,
+    },
+    /// The processor demand in an interval `L` exceeds `L` (EDF)
+    DemandExceeded {
+        interval: u64,
+        demand: u64,
+        blocking: u64,
+    },
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Failure::Overload { tot_util } => {
+                write!(f, "total CPU load {} is not below 1", tot_util)
+            }
+            Failure::DeadlineMiss { task, rt, deadline } => write!(
+                f,
+                "task {} misses its deadline, response time {} > {}",
+                task, rt, deadline
+            ),
+            Failure::DemandExceeded {
+                interval,
+                demand,
+                blocking,
+            } => write!(
+                f,
+                "processor demand {} (including blocking {}) exceeds the interval {}",
+                demand, blocking, interval
+            ),
+        }
+    }
+}
+
+/// System level schedulability verdict
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    pub schedulable: bool,
+    pub failures: Vec<Failure>,
+}
+
+/// Returns the schedulability verdict of the system, requiring
+/// `Ltot` < 1 and `R(t)` <= `D(t)` for all tasks
+pub fn schedulability(tot_util: Ratio, analysis: &Vec<TaskAnalysis>) -> Verdict {
+    let mut failures = vec![];
+
+    if tot_util >= Ratio::from(1) {
+        failures.push(Failure::Overload { tot_util });
+    }
+
+    for a in analysis {
+        let rt = match a.rt {
+            Ok(rt) => rt,
+            Err(miss) => a.jitter as u64 + miss.busy_period,
+        };
+        if rt > a.deadline as u64 {
+            failures.push(Failure::DeadlineMiss {
+                task: a.id.clone(),
+                rt,
+                deadline: a.deadline,
+            });
+        }
+    }
+
+    return Verdict {
+        schedulable: failures.is_empty(),
+        failures,
+    };
+}
+
 #[cfg(test)]
 mod parse_tests {
     use super::*;
@@ -184,6 +606,10 @@ mod parse_tests {
             prio: 1,
             deadline: 100,
             inter_arrival: 100,
+            vector: None,
+            jitter: 0,
+            stack: None,
+            core: 0,
             trace: Trace {
                 id: "T1".to_string(),
                 start: 0,
@@ -197,6 +623,10 @@ mod parse_tests {
             prio: 2,
             deadline: 200,
             inter_arrival: 200,
+            vector: None,
+            jitter: 0,
+            stack: None,
+            core: 0,
             trace: Trace {
                 id: "T2".to_string(),
                 start: 0,
@@ -228,6 +658,10 @@ mod parse_tests {
             prio: 3,
             deadline: 50,
             inter_arrival: 50,
+            vector: None,
+            jitter: 0,
+            stack: None,
+            core: 0,
             trace: Trace {
                 id: "T3".to_string(),
                 start: 0,
@@ -244,15 +678,288 @@ mod parse_tests {
         // builds a vector of tasks t1, t2, t3
         let tasks: Tasks = vec![t1, t2, t3];
 
+        let util = tot_util(&tasks);
+        assert_eq!(util, Ratio::new(17, 20));
+        assert_eq!(util.to_string(), "17/20 (0.8500)");
+
         let (ip, tr) = pre_analysis(&tasks);
-        let exact = srp_analysis(&tasks, &ip, &tr, false);
-        assert_eq!(exact[0].4, 90.0);
-        assert_eq!(exact[1].4, 60.0);
-        assert_eq!(exact[2].4, 0.0);
-
-        let approx = srp_analysis(&tasks, &ip, &tr, true);
-        assert_eq!(approx[0].4, 30.0);
-        assert_eq!(approx[1].4, 120.0);
-        assert_eq!(approx[2].4, 0.0);
+        let exact = srp_analysis(&tasks, &ip, &tr, &Config::default());
+        assert_eq!(exact[0].pre, Ok(90));
+        assert_eq!(exact[1].pre, Ok(60));
+        assert_eq!(exact[2].pre, Ok(0));
+
+        // compare rt_rec_calc.txt
+        let values: Vec<u64> = recurrence(&tasks[0], &tasks, &ip, &tr, &Config::default())
+            .iter()
+            .map(|i| i.value)
+            .collect();
+        assert_eq!(values, vec![10, 70, 100, 100]);
+
+        let record = srp_analysis(
+            &tasks,
+            &ip,
+            &tr,
+            &Config {
+                record: true,
+                ..Config::default()
+            },
+        );
+        let r_1 = &record[0].iterations[1];
+        assert_eq!(r_1.terms.len(), 2);
+        assert_eq!((r_1.terms[1].preemptions(), r_1.terms[1].cycles()), (1, 30));
+        assert_eq!(record[2].iterations.len(), 2);
+        assert!(exact[0].iterations.is_empty());
+
+        let approx = srp_analysis(
+            &tasks,
+            &ip,
+            &tr,
+            &Config {
+                approx: true,
+                ..Config::default()
+            },
+        );
+        assert_eq!(approx[0].pre, Ok(90));
+        assert_eq!(approx[1].pre, Ok(120));
+        assert_eq!(approx[2].pre, Ok(0));
+    }
+
+    #[test]
+    fn test_deadline_miss() {
+        let t1 = Task {
+            id: "T1".to_string(),
+            prio: 1,
+            deadline: 50,
+            inter_arrival: 100,
+            vector: None,
+            jitter: 0,
+            stack: None,
+            core: 0,
+            trace: Trace {
+                id: "T1".to_string(),
+                start: 0,
+                end: 20,
+                inner: vec![],
+            },
+        };
+
+        let t2 = Task {
+            id: "T2".to_string(),
+            prio: 2,
+            deadline: 30,
+            inter_arrival: 30,
+            vector: None,
+            jitter: 0,
+            stack: None,
+            core: 0,
+            trace: Trace {
+                id: "T2".to_string(),
+                start: 0,
+                end: 20,
+                inner: vec![],
+            },
+        };
+
+        let tasks: Tasks = vec![t1, t2];
+
+        let (ip, tr) = pre_analysis(&tasks);
+        let exact = srp_analysis(&tasks, &ip, &tr, &Config::default());
+        // R_0 = 20, R_1 = 20 + 20 = 40, R_2 = 20 + 40 = 60 > 50
+        assert_eq!(exact[0].rt, Err(DeadlineMiss { busy_period: 60 }));
+        assert_eq!(exact[0].pre, Err(DeadlineMiss { busy_period: 60 }));
+        assert_eq!(exact[1].rt, Ok(20));
+
+        let verdict = schedulability(tot_util(&tasks), &exact);
+        assert!(!verdict.schedulable);
+        assert_eq!(
+            verdict.failures,
+            vec![Failure::DeadlineMiss {
+                task: "T1".to_string(),
+                rt: 60,
+                deadline: 50,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_blocker() {
+        let tasks = tasks();
+        let (ip, tr) = pre_analysis(&tasks);
+        let analysis = srp_analysis(&tasks, &ip, &tr, &Config::default());
+        let blockers: Vec<Option<String>> = analysis
+            .iter()
+            .map(|a| a.blocker.as_ref().map(|b| b.to_string()))
+            .collect();
+        assert_eq!(
+            blockers,
+            vec![None, None, Some("T2 holding R2 at 12..16".to_string())]
+        );
+        assert_eq!(analysis[2].bt, 4);
+    }
+
+    #[test]
+    fn test_interference() {
+        let tasks = tasks();
+        let (ip, tr) = pre_analysis(&tasks);
+        for approx in [false, true] {
+            let config = Config {
+                approx,
+                ..Config::default()
+            };
+            let analysis = srp_analysis(&tasks, &ip, &tr, &config);
+            // T1 is preempted once by T2 and twice by T3 within 100
+            let breakdown: Vec<(String, u64, u64)> = analysis[0]
+                .interference
+                .iter()
+                .map(|t| (t.task.clone(), t.preemptions(), t.cycles()))
+                .collect();
+            assert_eq!(
+                breakdown,
+                vec![("T2".to_string(), 1, 30), ("T3".to_string(), 2, 60)]
+            );
+            assert_eq!(analysis[2].interference, vec![]);
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        let task = |id: &str, vector, trace: &str| Task {
+            vector: Some(vector),
+            ..test_task(id, 2, 100, trace)
+        };
+        let tasks: Tasks = vec![task("A", 1, "[A:0 10]"), task("B", 2, "[B:0 20]")];
+        let (ip, tr) = pre_analysis(&tasks);
+
+        let analysis = |policy| {
+            let config = Config {
+                policy,
+                ..Config::default()
+            };
+            srp_analysis(&tasks, &ip, &tr, &config)
+                .iter()
+                .map(|a| (a.bt, a.pre.unwrap(), a.rt.unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(analysis(Policy::Strict), vec![(0, 0, 10), (0, 0, 20)]);
+        assert_eq!(
+            analysis(Policy::SameOrHigher),
+            vec![(0, 20, 30), (0, 10, 30)]
+        );
+        // A is served first but may have to wait for B to complete
+        assert_eq!(
+            analysis(Policy::VectorOrder),
+            vec![(20, 0, 30), (0, 10, 30)]
+        );
+    }
+
+    #[test]
+    fn test_jitter() {
+        let task = |id: &str, prio, deadline, trace: &str, jitter| Task {
+            jitter,
+            ..test_task(id, prio, deadline, trace)
+        };
+        let tasks: Tasks = vec![
+            task("L", 1, 100, "[L:0 40]", 3),
+            task("H", 2, 50, "[H:0 10]", 5),
+        ];
+        let (ip, tr) = pre_analysis(&tasks);
+        let config = Config {
+            record: true,
+            ..Config::default()
+        };
+        let analysis = srp_analysis(&tasks, &ip, &tr, &config);
+
+        // R_1 = 40 + ceil((40 + 5)/50) * 10 = 50, R_2 = 40 + ceil((50 + 5)/50) * 10 = 60
+        let values: Vec<u64> = analysis[0].iterations.iter().map(|i| i.value).collect();
+        assert_eq!(values, vec![40, 50, 60, 60]);
+        assert_eq!(analysis[0].rt, Ok(3 + 60));
+        assert_eq!(analysis[1].rt, Ok(5 + 10));
+
+        // the jitter alone makes L miss its deadline
+        let mut tasks = tasks;
+        tasks[0].jitter = 45;
+        let analysis = srp_analysis(&tasks, &ip, &tr, &config);
+        assert_eq!(analysis[0].rt, Err(DeadlineMiss { busy_period: 60 }));
+        assert!(!schedulability(tot_util(&tasks), &analysis).schedulable);
+    }
+
+    #[test]
+    fn test_long_recurrence() {
+        // converges after 60000 iterations, at 60000 + 60000 * 59999
+        let tasks: Tasks = vec![
+            test_task("L", 1, 4_000_000_000, "[L:0 60000]"),
+            test_task("H", 2, 60_000, "[H:0 59999]"),
+        ];
+        let (ip, tr) = pre_analysis(&tasks);
+        let analysis = srp_analysis(&tasks, &ip, &tr, &Config::default());
+        assert_eq!(analysis[0].rt, Ok(3_600_000_000));
+        assert_eq!(analysis[0].interference[0].preemptions(), 60_000);
+    }
+
+    #[test]
+    fn test_overheads() {
+        let tasks: Tasks = vec![
+            test_task("L", 1, 100, "[L:0 [R:10 20] 30]"),
+            test_task("H", 2, 50, "[H:0 [R:0 2] 10]"),
+        ];
+        let (ip, tr) = pre_analysis(&tasks);
+        let config = Config {
+            overheads: Overheads {
+                preemption: 3,
+                lock: 1,
+                unlock: 1,
+                dispatch: 2,
+            },
+            ..Config::default()
+        };
+        let analysis = srp_analysis(&tasks, &ip, &tr, &config);
+
+        // C(L) = 30 + 2 + (1 + 1), preempted twice by H, each 10 + 2 + (1 + 1) + 3
+        assert_eq!(analysis[0].wcet, 34);
+        assert_eq!(analysis[0].rt, Ok(34 + 2 * 17));
+        assert_eq!(analysis[0].overhead, Some(68 - 40));
+        // blocked by L holding R, including its lock and unlock
+        assert_eq!(analysis[1].bt, 10 + 2);
+        assert_eq!(analysis[1].rt, Ok(14 + 12));
+        assert_eq!(analysis[1].overhead, Some(26 - 20));
+        assert_eq!(
+            tot_util_overheads(&tasks, &config.overheads),
+            Ratio::new(62, 100)
+        );
+
+        let analysis = srp_analysis(&tasks, &ip, &tr, &Config::default());
+        assert_eq!(analysis[0].overhead, Some(0));
+    }
+
+    #[test]
+    fn test_overhead_blocker() {
+        let tasks: Tasks = vec![
+            Task {
+                vector: Some(1),
+                ..test_task("A", 2, 1000, "[A:0 [R:0 1] 5]")
+            },
+            Task {
+                vector: Some(2),
+                ..test_task("B", 2, 1000, "[B:0 [X:0 1] [Y:1 2] [Z:2 3] 9]")
+            },
+            test_task("L", 1, 1000, "[L:0 [R:0 10] 10]"),
+        ];
+        let (ip, tr) = pre_analysis(&tasks);
+        let mut config = Config {
+            policy: Policy::VectorOrder,
+            ..Config::default()
+        };
+        // without overheads, L holding R (10) outweighs B (9)
+        let analysis = srp_analysis(&tasks, &ip, &tr, &config);
+        assert_eq!(analysis[0].blocker.as_ref().unwrap().task, "L");
+        assert_eq!(analysis[0].bt, 10);
+
+        // with overheads, B costs 9 + 3 * (5 + 5) = 39, L holding R 10 + 5 + 5
+        config.overheads.lock = 5;
+        config.overheads.unlock = 5;
+        let analysis = srp_analysis(&tasks, &ip, &tr, &config);
+        assert_eq!(analysis[0].blocker.as_ref().unwrap().task, "B");
+        assert_eq!(analysis[0].bt, 39);
     }
 }
@@ -0,0 +1,149 @@
+use crate::common::*;
+
+// Shared stack analysis under SRP.
+//
+// Under SRP a started task is never blocked, so all tasks may share a single
+// stack and the stack depth is bounded by the deepest chain of preemptions.
+// Task `h` may preempt task `t` only if `P(h)` exceeds the preemption
+// threshold of `t`, the lowest system ceiling while `t` executes: `P(t)` if
+// `t` ever runs outside of its critical sections, otherwise the lowest
+// ceiling `π(r)` of the resources held throughout. Tasks of the same priority
+// never preempt each other under SRP, regardless of the interference policy
+// assumed by the response time analysis.
+
+/// Stack usage of a single task
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskStack {
+    pub id: String,
+    /// The stack usage of the task, `None` if not given
+    pub stack: Option<u32>,
+    /// The lowest system ceiling while the task executes
+    pub threshold: u8,
+    /// The worst-case stack depth of any preemption chain starting with the task
+    pub depth: u64,
+    /// The task and the tasks stacked on top of it in the worst case
+    pub chain: Vec<String>,
+}
+
+/// Worst-case depth of the shared stack
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackAnalysis {
+    pub tasks: Vec<TaskStack>,
+    pub depth: u64,
+    pub chain: Vec<String>,
+}
+
+/// Returns true if any task gives its stack usage
+pub fn has_stack(tasks: &Tasks) -> bool {
+    tasks.iter().any(|t| t.stack.is_some())
+}
+
+/// Returns the preemption threshold of a task, see above
+pub fn threshold(task: &Task, ip: &IdPrio) -> u8 {
+    min_ceiling(&task.trace, task.prio, ip).unwrap_or(task.prio)
+}
+
+// Returns the lowest ceiling over the execution of `trace`, where `ceiling`
+// is the system ceiling on entry, `None` if `trace` has zero length
+fn min_ceiling(trace: &Trace, ceiling: u8, ip: &IdPrio) -> Option<u8> {
+    let length = trace.end.saturating_sub(trace.start);
+    let covered: u32 = trace
+        .inner
+        .iter()
+        .map(|i| i.end.saturating_sub(i.start))
+        .sum();
+    let own = if covered < length {
+        Some(ceiling)
+    } else {
+        None
+    };
+
+    trace
+        .inner
+        .iter()
+        .filter_map(|i| {
+            let inner = ip.get(&i.id).map_or(ceiling, |c| ceiling.max(*c));
+            min_ceiling(i, inner, ip)
+        })
+        .chain(own)
+        .min()
+}
+
+/// Computes the worst-case depth of the shared stack. Tasks without a given
+/// stack usage count as 0.
+pub fn stack_analysis(tasks: &Tasks, ip: &IdPrio) -> StackAnalysis {
+    let thresholds: Vec<u8> = tasks.iter().map(|t| threshold(t, ip)).collect();
+
+    // by decreasing priority, so that any task preempting `t` is done first
+    let mut order: Vec<usize> = (0..tasks.len()).collect();
+    order.sort_by(|a, b| tasks[*b].prio.cmp(&tasks[*a].prio));
+
+    let mut deepest: Vec<(u64, Vec<String>)> = vec![(0, vec![]); tasks.len()];
+    for &i in &order {
+        let t = &tasks[i];
+        let top = (0..tasks.len())
+            .filter(|h| tasks[*h].prio > thresholds[i])
+            .map(|h| &deepest[h])
+            .max_by_key(|(depth, _)| *depth);
+
+        let mut chain = vec![t.id.clone()];
+        let mut depth = t.stack.unwrap_or(0) as u64;
+        if let Some((top_depth, top_chain)) = top {
+            depth += top_depth;
+            chain.extend(top_chain.iter().cloned());
+        }
+        deepest[i] = (depth, chain);
+    }
+
+    let task_stacks: Vec<TaskStack> = tasks
+        .iter()
+        .zip(thresholds)
+        .zip(&deepest)
+        .map(|((t, threshold), (depth, chain))| TaskStack {
+            id: t.id.clone(),
+            stack: t.stack,
+            threshold,
+            depth: *depth,
+            chain: chain.clone(),
+        })
+        .collect();
+    let (depth, chain) = deepest
+        .into_iter()
+        .max_by_key(|(depth, _)| *depth)
+        .unwrap_or((0, vec![]));
+
+    StackAnalysis {
+        tasks: task_stacks,
+        depth,
+        chain,
+    }
+}
+
+#[cfg(test)]
+mod stack_tests {
+    use super::*;
+    use crate::tasks::tasks;
+    use crate::trace_notation::parse_trace;
+
+    #[test]
+    fn test_stack() {
+        let mut tasks = tasks();
+        for (t, stack) in tasks.iter_mut().zip([100, 200, 50]) {
+            t.stack = Some(stack);
+        }
+        let (ip, _) = pre_analysis(&tasks);
+        let s = stack_analysis(&tasks, &ip);
+        assert_eq!(s.depth, 350);
+        assert_eq!(s.chain, vec!["T1", "T2", "T3"]);
+        assert_eq!(s.tasks[1].threshold, 2);
+
+        // T2 runs entirely within R2 (π = 3), so T3 can no longer preempt it
+        tasks[1].trace = parse_trace("[T2:0 [R2:0 [R1:12 16] 30] 30]").unwrap();
+        let (ip, _) = pre_analysis(&tasks);
+        let s = stack_analysis(&tasks, &ip);
+        let thresholds: Vec<u8> = s.tasks.iter().map(|t| t.threshold).collect();
+        assert_eq!(thresholds, vec![1, 3, 3]);
+        assert_eq!(s.depth, 300);
+        assert_eq!(s.chain, vec!["T1", "T2"]);
+    }
+}
@@ -6,6 +6,10 @@ pub fn tasks() -> Vec<Task> {
         prio: 1,
         deadline: 100,
         inter_arrival: 100,
+        vector: None,
+        jitter: 0,
+        stack: None,
+        core: 0,
         trace: Trace {
             id: "T1".to_string(),
             start: 0,
@@ -19,6 +23,10 @@ pub fn tasks() -> Vec<Task> {
         prio: 2,
         deadline: 200,
         inter_arrival: 200,
+        vector: None,
+        jitter: 0,
+        stack: None,
+        core: 0,
         trace: Trace {
             id: "T2".to_string(),
             start: 0,
@@ -50,6 +58,10 @@ pub fn tasks() -> Vec<Task> {
         prio: 3,
         deadline: 50,
         inter_arrival: 50,
+        vector: None,
+        jitter: 0,
+        stack: None,
+        core: 0,
         trace: Trace {
             id: "T3".to_string(),
             start: 0,
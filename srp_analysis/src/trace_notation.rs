@@ -0,0 +1,245 @@
+use crate::common::*;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use std::fmt;
+
+// Parser and pretty-printer for the trace notation of HOME_EXAM.md,
+// annotated with timestamps:
+//
+// `[T2:0 [R1:10 [R2:12 16] 20] [R1:22 28] 30]`
+//
+// Each section `[id:start ... end]` holds its identifier, the start
+// timestamp, any nested critical sections and the end timestamp.
+
+/// A syntax error in the trace notation, with 1-based line and column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub col: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.msg)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a trace in bracket notation into a `Trace` tree
+pub fn parse_trace(s: &str) -> Result<Trace, ParseError> {
+    let mut p = Parser {
+        chars: s.chars().collect(),
+        pos: 0,
+        line: 1,
+        col: 1,
+    };
+    p.skip_ws();
+    let trace = p.section()?;
+    p.skip_ws();
+    if let Some(c) = p.peek() {
+        return Err(p.error(format!("unexpected `{}` after end of trace", c)));
+    }
+    Ok(trace)
+}
+
+/// Returns the trace in bracket notation
+pub fn format_trace(trace: &Trace) -> String {
+    trace.to_string()
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}:{}", self.id, self.start)?;
+        for i in &self.inner {
+            write!(f, " {}", i)?;
+        }
+        write!(f, " {}]", self.end)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    col: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    fn error(&self, msg: String) -> ParseError {
+        ParseError {
+            line: self.line,
+            col: self.col,
+            msg,
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError {
+        match self.peek() {
+            Some(c) => self.error(format!("expected {}, found `{}`", expected, c)),
+            None => self.error(format!("expected {}, found end of input", expected)),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if self.peek() == Some(c) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", c)))
+        }
+    }
+
+    // `[id:start section* end]`
+    fn section(&mut self) -> Result<Trace, ParseError> {
+        self.expect('[')?;
+        self.skip_ws();
+        let id = self.ident()?;
+        self.skip_ws();
+        self.expect(':')?;
+        self.skip_ws();
+        let start = self.number("start timestamp")?;
+
+        let mut inner = vec![];
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some('[') => inner.push(self.section()?),
+                Some(c) if c.is_ascii_digit() => break,
+                _ => return Err(self.unexpected("`[` or end timestamp")),
+            }
+        }
+        let end = self.number("end timestamp")?;
+        self.skip_ws();
+        self.expect(']')?;
+
+        Ok(Trace {
+            id,
+            start,
+            end,
+            inner,
+        })
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        let mut id = String::new();
+        while let Some(c) = self.peek() {
+            if !(c.is_alphanumeric() || c == '_') {
+                break;
+            }
+            id.push(c);
+            self.bump();
+        }
+        if id.is_empty() {
+            Err(self.unexpected("identifier"))
+        } else {
+            Ok(id)
+        }
+    }
+
+    fn number(&mut self, what: &str) -> Result<u32, ParseError> {
+        let (line, col) = (self.line, self.col);
+        let mut digits = String::new();
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            digits.push(c);
+            self.bump();
+        }
+        if digits.is_empty() {
+            return Err(self.unexpected(what));
+        }
+        digits.parse().map_err(|_| ParseError {
+            line,
+            col,
+            msg: format!("{} `{}` does not fit in u32", what, digits),
+        })
+    }
+}
+
+/// Deserializes a `Trace` given either as a structure or in bracket notation
+pub fn deserialize_trace<'de, D>(deserializer: D) -> Result<Trace, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct TraceVisitor;
+
+    impl<'de> Visitor<'de> for TraceVisitor {
+        type Value = Trace;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a trace structure or a trace in bracket notation")
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<Trace, E> {
+            parse_trace(s).map_err(|e| E::custom(format!("trace notation {}", e)))
+        }
+
+        fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<Trace, M::Error> {
+            Trace::deserialize(de::value::MapAccessDeserializer::new(map))
+        }
+    }
+
+    deserializer.deserialize_any(TraceVisitor)
+}
+
+#[cfg(test)]
+mod trace_notation_tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let s = "[T2:0 [R1:10 [R2:12 16] 20] [R1:22 28] 30]";
+        let trace = parse_trace(s).unwrap();
+        assert_eq!(trace.id, "T2");
+        assert_eq!(trace.end, 30);
+        assert_eq!(trace.inner.len(), 2);
+        assert_eq!(trace.inner[0].inner[0].id, "R2");
+        assert_eq!(trace.inner[0].inner[0].start, 12);
+        assert_eq!(format_trace(&trace), s);
+
+        let spaced = parse_trace("\n  [ T3 : 0\n    [R2: 10 20 ]\n  30 ]\n").unwrap();
+        assert_eq!(format_trace(&spaced), "[T3:0 [R2:10 20] 30]");
+    }
+
+    #[test]
+    fn test_errors() {
+        let e = parse_trace("[T1:0\n  [R1 2 4] 10]").unwrap_err();
+        assert_eq!((e.line, e.col), (2, 7));
+        assert_eq!(e.msg, "expected `:`, found `2`");
+
+        let e = parse_trace("[T1:0 [R1:2 4] 10").unwrap_err();
+        assert_eq!((e.line, e.col), (1, 18));
+        assert_eq!(e.msg, "expected `]`, found end of input");
+
+        let e = parse_trace("[T1:0 10] x").unwrap_err();
+        assert_eq!(e.to_string(), "1:11: unexpected `x` after end of trace");
+    }
+}
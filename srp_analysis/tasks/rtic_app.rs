@@ -0,0 +1,37 @@
+//! A sketch of an RTIC application, for `--import-rtic`
+#![no_main]
+#![no_std]
+
+use panic_halt as _;
+
+#[rtic::app(device = stm32f4::stm32f411, dispatchers = [EXTI1])]
+mod app {
+    #[shared]
+    struct Shared {
+        r1: u64,
+        r2: u64,
+    }
+
+    #[local]
+    struct Local {}
+
+    #[init]
+    fn init(_: init::Context) -> (Shared, Local, init::Monotonics) {
+        (Shared { r1: 0, r2: 0 }, Local {}, init::Monotonics())
+    }
+
+    #[task(binds = EXTI0, priority = 1)]
+    fn t1(_: t1::Context) {}
+
+    #[task(binds = EXTI2, priority = 2, shared = [r1, r2])]
+    fn t2(mut cx: t2::Context) {
+        cx.shared.r1.lock(|r1| {
+            cx.shared.r2.lock(|r2| *r1 += *r2);
+        });
+    }
+
+    #[task(binds = EXTI3, priority = 3, shared = [r2])]
+    fn t3(mut cx: t3::Context) {
+        cx.shared.r2.lock(|r2| *r2 += 1);
+    }
+}